@@ -1,6 +1,6 @@
 #!/bin/bash
 
-# Requirements: pkill, curl, go, jq, grpcurl
+# Requirements: pkill, curl, go, jq
 
 BIN_DIR=./zcv
 
@@ -12,12 +12,12 @@ usage() {
   echo "  download         Download and install the binaries"
   echo "  set-node-config  Download genesis and configure as a full node"
   echo "  start            Start and join as a standard full node"
-  echo "  promote          Promote to validator"
+  echo "  promote          Promote to validator (needs --authority-seed)"
   echo "  show-validators  Show the validator set"
   echo "Coordinator Commands:"
   echo "  coordinate       Configure as coordinator"
-  echo "  set-election     Set the Election Definition"
-  echo "  lock             Lock the Blockchain against further updates"
+  echo "  set-election     Set the Election Definition (needs --authority-seed)"
+  echo "  lock             Lock the election of --election-json (needs --authority-seed)"
   echo "  unsafe-reset     Delete all data and reset"
 
   echo ""
@@ -31,7 +31,8 @@ download() {
 
   curl -L -o zcv/vote-cometbft "https://github.com/hhanh00/zcv/releases/download/zcvlib-v0.6.0/vote-cometbft"
   curl -L -o zcv/protos/vote.proto "https://raw.githubusercontent.com/hhanh00/zcv/refs/tags/zcvlib-v0.6.0/zcvlib/protos/vote.proto"
-  chmod +x zcv/vote-cometbft
+  curl -L -o zcv/admin "https://github.com/hhanh00/zcv/releases/download/zcvlib-v0.6.0/admin"
+  chmod +x zcv/vote-cometbft zcv/admin
   $BIN_DIR/cometbft init --home cometbft
 }
 
@@ -69,10 +70,7 @@ set_election() {
     usage
   fi
 
-  ELECTION=$(cat "../$ELECTION_JSON")
-  ELECTION_REQ=$(jq -n --arg election "$ELECTION" '{"election": $election}')
-
-  grpcurl --plaintext --proto zcv/protos/vote.proto -d "$ELECTION_REQ" localhost:9010 cash.z.vote.sdk.rpc.VoteStreamer/SetElection
+  admin set-election --election-file "../$ELECTION_JSON"
 }
 
 start() {
@@ -86,13 +84,32 @@ start() {
 
 promote() {
   echo "Promoting to validator..."
-  PK=$(cat cometbft/config/priv_validator_key.json | jq .pub_key.value)
-  grpcurl --plaintext --proto zcv/protos/vote.proto -d "{\"pub_key\": $PK, \"power\": \"10\"}" localhost:9010 cash.z.vote.sdk.rpc.VoteStreamer/AddValidator
+  PK=$(cat cometbft/config/priv_validator_key.json | jq -r .pub_key.value)
+  admin add-validator --pub-key "$PK" --power 10
 }
 
 lock() {
   echo "Locking..."
-  grpcurl --plaintext --proto zcv/protos/vote.proto -d "{}" localhost:9010 cash.z.vote.sdk.rpc.VoteStreamer/Lock
+  missing=()
+  [[ -z "$ELECTION_JSON" ]] && missing+=("--election-json")
+
+  if [[ ${#missing[@]} -gt 0 ]]; then
+    echo "Error: missing required flags: ${missing[*]}" >&2
+    usage
+  fi
+
+  admin lock --domain "$(jq -r .domain "../$ELECTION_JSON")"
+}
+
+# Sign an admin message with the authority seed and relay it through
+# the admin service of the local node
+admin() {
+  if [[ -z "$AUTHORITY_SEED" ]]; then
+    echo "Error: missing required flags: --authority-seed" >&2
+    usage
+  fi
+  CHAIN_ID=$(jq -r .chain_id cometbft/config/genesis.json)
+  $BIN_DIR/admin --seed "$AUTHORITY_SEED" --chain-id "$CHAIN_ID" ${ZCV_ADMIN_TOKEN:+--admin-token "$ZCV_ADMIN_TOKEN"} "$@"
 }
 
 show_validators() {
@@ -125,6 +142,8 @@ main() {
         ELECTION_JSON="$2"; shift 2 ;;
       --external-ip)
         EXTERNAL_IP="$2"; shift 2 ;;
+      --authority-seed)
+        AUTHORITY_SEED="$2"; shift 2 ;;
       --seed)
         SEED="$2"; shift 2 ;;
       --genesis-url)
@@ -3,7 +3,5 @@
 yq eval -o json "$1.yml" > "$1.json"
 ELECTION_SEED="stool rich together paddle together pool raccoon promote attitude peasant latin concert"
 ./target/release/creator --election-file "$1.json" --seed "$ELECTION_SEED" --output-file "$1-pub.json"
-ELECTION=$(jq -c . "$1-pub.json")
-ELECTION_REQ=$(jq -n --arg election "$ELECTION" '{"election": $election}')
-
-grpcurl -d "$ELECTION_REQ" --proto zcvlib/protos/vote.proto --plaintext localhost:9010 cash.z.vote.sdk.rpc.VoteStreamer/SetElection
+CHAIN_ID=$(curl -s localhost:26657/status | jq -r .result.node_info.network)
+./target/release/admin --seed "$ELECTION_SEED" --chain-id "$CHAIN_ID" ${ZCV_ADMIN_TOKEN:+--admin-token "$ZCV_ADMIN_TOKEN"} set-election --election-file "$1-pub.json"
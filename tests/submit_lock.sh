@@ -1,3 +1,6 @@
 #!/bin/sh
 
-grpcurl -d '{}' --proto zcvlib/protos/vote.proto --plaintext localhost:9010 cash.z.vote.sdk.rpc.VoteStreamer/Lock
+ELECTION_SEED="stool rich together paddle together pool raccoon promote attitude peasant latin concert"
+CHAIN_ID=$(curl -s localhost:26657/status | jq -r .result.node_info.network)
+DOMAIN=$(jq -r .domain "$1-pub.json")
+./target/release/admin --seed "$ELECTION_SEED" --chain-id "$CHAIN_ID" ${ZCV_ADMIN_TOKEN:+--admin-token "$ZCV_ADMIN_TOKEN"} lock --domain "$DOMAIN"
@@ -2,13 +2,14 @@
 
 import json
 import logging
+import os
 import subprocess
 from pathlib import Path
 
 logger = logging.getLogger("zcv.election")
 
-GRPC_URL = "localhost:9010"
-SERVICE = "cash.z.vote.sdk.rpc.VoteStreamer"
+GRPC_URL = "localhost:9011"
+SERVICE = "cash.z.vote.sdk.rpc.AdminService"
 
 
 def set_election(config) -> None:
@@ -103,9 +104,12 @@ def grpc_call(method: str, data: str, proto_path: Path) -> bool:
         str(proto_path),
         "-d",
         data,
-        GRPC_URL,
-        f"{SERVICE}/{method}",
     ]
+    # Bearer token of the admin service, see admin_token in zcv.toml
+    token = os.environ.get("ZCV_ADMIN_TOKEN")
+    if token:
+        cmd += ["-H", f"authorization: Bearer {token}"]
+    cmd += [GRPC_URL, f"{SERVICE}/{method}"]
 
     try:
         subprocess.run(cmd, check=True, capture_output=True, text=True)
@@ -0,0 +1,57 @@
+// Throughput of check_tx with a tx that is rejected right after decoding,
+// so that the cost is dominated by the dispatch of the ABCI handler.
+// "check_tx per call runtime" rebuilds a Tokio runtime for every call
+// like the handlers used to do, "check_tx shared runtime" is the
+// current path
+//
+// cargo bench --bench check_tx
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use prost::Message;
+use tendermint_abci::Application;
+use tendermint_proto::abci::RequestCheckTx;
+use zcvlib::{
+    context::BFTContext,
+    server::Server,
+    vote_rpc::{ElectionId, VoteMessage, vote_message::TypeOneof},
+};
+
+fn server() -> Server {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let ctx = BFTContext::new("bench.db", "", 0, true).await?;
+        Server::new(ctx.context.pool, "", true).await
+    })
+    .unwrap()
+}
+
+fn request() -> RequestCheckTx {
+    let tx = VoteMessage {
+        type_oneof: Some(TypeOneof::Lock(ElectionId::default())),
+    }
+    .encode_to_vec();
+    RequestCheckTx {
+        tx: tx.into(),
+        ..RequestCheckTx::default()
+    }
+}
+
+fn check_tx_per_call_runtime(c: &mut Criterion) {
+    let app = server();
+    c.bench_function("check_tx per call runtime", |b| {
+        b.iter(|| {
+            let _rt = tokio::runtime::Runtime::new().unwrap();
+            app.check_tx(request())
+        })
+    });
+}
+
+fn check_tx_shared_runtime(c: &mut Criterion) {
+    let app = server();
+    c.bench_function("check_tx shared runtime", |b| {
+        b.iter(|| app.check_tx(request()))
+    });
+}
+
+criterion_group!(benches, check_tx_per_call_runtime, check_tx_shared_runtime);
+criterion_main!(benches);
@@ -0,0 +1,166 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use clap::{Parser, Subcommand};
+use prost::Message;
+use tonic::Request;
+use zcvlib::{
+    authority::{AdminScope, authority_key_from_seed, sign_admin_message},
+    lwd::fetch_initial_roots,
+    pod::{ElectionPropsPub, network_from_name},
+    vote_rpc::{
+        Authority, Election, ElectionId, SignedMessage, Validator, VoteMessage,
+        admin_service_client::AdminServiceClient, vote_message::TypeOneof,
+    },
+};
+
+// Signs the admin messages with the seed of the election authority
+// and sends them to the admin service of a node, which relays them.
+// The seed stays on the machine of the operator
+#[derive(Parser, Debug)]
+pub struct Config {
+    #[clap(short, long, value_parser)]
+    pub seed: String,
+    // chain_id of the genesis.json of the vote chain
+    #[clap(short, long, value_parser)]
+    pub chain_id: String,
+    // Network of the keys, "main" or "test". SetElection uses
+    // the network of the election
+    #[clap(short, long, value_parser, default_value = "main")]
+    pub network: String,
+    #[clap(short, long, value_parser, default_value = "http://127.0.0.1:9011")]
+    pub admin_url: String,
+    #[clap(short = 't', long, value_parser)]
+    pub admin_token: Option<String>,
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    // Election file made by `creator`
+    SetElection {
+        #[clap(short, long, value_parser)]
+        election_file: String,
+        #[clap(short, long, value_parser, default_value = "https://zec.rocks")]
+        lwd_url: String,
+    },
+    // Validator keys are base64, like in priv_validator_key.json
+    AddValidator {
+        #[clap(short, long, value_parser)]
+        pub_key: String,
+        #[clap(long, value_parser)]
+        power: u32,
+    },
+    RemoveValidator {
+        #[clap(short, long, value_parser)]
+        pub_key: String,
+    },
+    SetValidatorPower {
+        #[clap(short, long, value_parser)]
+        pub_key: String,
+        #[clap(long, value_parser)]
+        power: u32,
+    },
+    // Hex key of the new authority
+    RotateAuthority {
+        #[clap(short, long, value_parser)]
+        pub_key: String,
+    },
+    // Hex domain of the election
+    Lock {
+        #[clap(short, long, value_parser)]
+        domain: String,
+    },
+}
+
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let subscriber = tracing_subscriber::fmt()
+        .with_ansi(false)
+        .compact()
+        .finish();
+    let _ = tracing::subscriber::set_global_default(subscriber);
+
+    let Config {
+        seed,
+        chain_id,
+        network,
+        admin_url,
+        admin_token,
+        command,
+    } = Config::parse();
+    let validator = |pub_key: &str, power: u32| {
+        Ok::<_, anyhow::Error>(Validator {
+            pub_key: BASE64_STANDARD.decode(pub_key)?,
+            power,
+        })
+    };
+    let mut network = network_from_name(&network)?;
+    // The election the message acts on, see ServerState::open_admin_message
+    let mut domain = vec![];
+    let m = match command {
+        Command::SetElection {
+            election_file,
+            lwd_url,
+        } => {
+            let mut e: ElectionPropsPub =
+                serde_json::from_reader(std::fs::File::open(election_file)?)?;
+            network = e.network()?;
+            domain = e.domain.clone();
+            if e.authority.is_empty() {
+                e.authority = authority_key_from_seed(&network, &seed)?.to_vec();
+            }
+            let (nf_root, cmx_tree_state) = fetch_initial_roots(&lwd_url, &e.pir, e.end).await?;
+            TypeOneof::SetElection(Election {
+                election: serde_json::to_string(&e)?,
+                nf_root,
+                cmx_tree_state,
+            })
+        }
+        Command::AddValidator { pub_key, power } => {
+            TypeOneof::AddValidator(validator(&pub_key, power)?)
+        }
+        Command::RemoveValidator { pub_key } => TypeOneof::RemoveValidator(validator(&pub_key, 0)?),
+        Command::SetValidatorPower { pub_key, power } => {
+            TypeOneof::SetValidatorPower(validator(&pub_key, power)?)
+        }
+        Command::RotateAuthority { pub_key } => TypeOneof::RotateAuthority(Authority {
+            pub_key: hex::decode(pub_key)?,
+        }),
+        Command::Lock { domain: d } => {
+            domain = hex::decode(d)?;
+            TypeOneof::Lock(ElectionId {
+                domain: domain.clone(),
+            })
+        }
+    };
+
+    let message = VoteMessage {
+        type_oneof: Some(m),
+    }
+    .encode_to_vec();
+    // Nonces must increase, use the time to avoid reusing
+    // a nonce of a message still in the mempool
+    let nonce = SystemTime::now().duration_since(UNIX_EPOCH)?.as_micros() as u64;
+    let scope = AdminScope {
+        chain_id: &chain_id,
+        domain: &domain,
+    };
+    let signature = sign_admin_message(&network, &seed, &scope, nonce, &message)?;
+    let mut request = Request::new(SignedMessage {
+        message,
+        nonce,
+        signature: signature.to_vec(),
+    });
+    if let Some(token) = admin_token {
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {token}").parse()?);
+    }
+    let mut client = AdminServiceClient::connect(admin_url).await?;
+    let hash = client.submit(request).await?.into_inner();
+    tracing::info!("Admin tx {}", hex::encode(hash.hash));
+    Ok(())
+}
@@ -12,9 +12,10 @@ use crate::context::Context;
 use crate::db::{get_election, get_election_height};
 use crate::lwd::{VoteClient, connect};
 use crate::pod::{ElectionProps, ElectionPropsPub};
+use crate::pow::{MAX_POW_BITS, mint_pow_stamp};
 use crate::tiu;
-use crate::vote::VoteResultItem;
-use crate::vote_rpc::Empty;
+use crate::vote::{BallotStatusItem, VoteResultItem};
+use crate::vote_rpc::{ElectionId, Hash, VoteRange};
 use crate::vote_rpc::vote_streamer_client::VoteStreamerClient;
 
 pub fn compile_election_def(election_json: String, seed: String) -> Result<String> {
@@ -43,15 +44,32 @@ pub async fn client_delete_election_data(context: &Context, new_account: Option<
     Ok(())
 }
 
-pub async fn scan_ballots(id_account: u32, context: &Context) -> Result<()> {
+// Scan the ballots up to the latest vote height. With follow, keep
+// scanning the new ballots as they are finalized, it does not return
+// unless the vote server closes the connection
+pub async fn scan_ballots(id_account: u32, follow: bool, context: &Context) -> Result<()> {
     let mut conn = context.connect().await?;
     let (election, _, _) = get_election(&mut conn).await?;
-    let ep = Endpoint::from_shared(context.election_url.clone())?;
-    let mut client = VoteStreamerClient::connect(ep).await?;
+    let mut client = connect_to_vote_server(context).await?;
     let pir_client = PirClient::connect(&election.pir).await?;
     let start = get_election_height(&mut conn).await? + 1;
+    if follow {
+        crate::lwd::follow_ballots(
+            &Network::MainNetwork,
+            &mut conn,
+            &mut client,
+            &pir_client,
+            id_account,
+            &election.domain,
+            start,
+        )
+        .await?;
+        return Ok(());
+    }
     let rep = client
-        .get_latest_vote_height(Request::new(Empty {}))
+        .get_latest_vote_height(Request::new(ElectionId {
+            domain: election.domain.clone(),
+        }))
         .await?;
     let end = rep.into_inner().height;
     crate::lwd::scan_ballots(
@@ -60,6 +78,7 @@ pub async fn scan_ballots(id_account: u32, context: &Context) -> Result<()> {
         &mut client,
         &pir_client,
         id_account,
+        &election.domain,
         start,
         end,
     )
@@ -71,18 +90,31 @@ pub async fn decode_ballots(election_seed: String, context: &Context) -> Result<
     let mut conn = context.connect().await?;
     let ep = Endpoint::from_shared(context.election_url.clone())?;
     let mut client = VoteStreamerClient::connect(ep).await?;
-    let election = client.get_election(Request::new(Empty {})).await?.into_inner();
+    // The election we imported, or the only one of the server
+    let domain = get_election(&mut conn)
+        .await
+        .map(|(e, ..)| e.domain)
+        .unwrap_or_default();
+    let election = client
+        .get_election(Request::new(ElectionId { domain }))
+        .await?
+        .into_inner();
     let election: ElectionPropsPub = serde_json::from_str(&election.election)?;
     let start = election.end;
     let rep = client
-        .get_latest_vote_height(Request::new(Empty {}))
+        .get_latest_vote_height(Request::new(ElectionId {
+            domain: election.domain.clone(),
+        }))
         .await?;
     let end = rep.into_inner().height;
+    // The chain does not accept ballots after the deadline
+    let end = election.closing_vote_height().map_or(end, |close| end.min(close));
     crate::lwd::decode_ballots(
         &Network::MainNetwork,
         &mut conn,
         &mut client,
         &election_seed,
+        &election.domain,
         start,
         end,
     )
@@ -90,6 +122,20 @@ pub async fn decode_ballots(election_seed: String, context: &Context) -> Result<
     Ok(())
 }
 
+pub async fn get_ballot_status(sighash: Vec<u8>, context: &Context) -> Result<BallotStatusItem> {
+    let mut client = connect_to_vote_server(context).await?;
+    let status = client
+        .get_ballot_status(Request::new(Hash { hash: sighash }))
+        .await?
+        .into_inner();
+    Ok(BallotStatusItem {
+        status: status.status().as_str_name().to_lowercase(),
+        height: status.height,
+        itx: status.itx,
+        reason: status.reason,
+    })
+}
+
 pub async fn collect_results(context: &Context) -> Result<Vec<VoteResultItem>> {
     let mut conn = context.connect().await?;
     let res = crate::vote::collect_results(&mut conn).await?;
@@ -109,13 +155,46 @@ async fn submit_ballot(ballot: Ballot, context: &Context) -> Result<Vec<u8>> {
     let mut ballot_bytes = vec![];
     ballot.write(&mut ballot_bytes)?;
     let mut client = connect_to_vote_server(context).await?;
+    let domain = ballot.data.domain.to_vec();
+    let election = client
+        .get_election(Request::new(ElectionId {
+            domain: domain.clone(),
+        }))
+        .await?
+        .into_inner();
+    let election: ElectionPropsPub = serde_json::from_str(&election.election)?;
+    // The anchor is the root of the cmx tree at the height we scanned to.
+    // It must be one the vote chain had, or the ballot is rejected
+    let mut conn = context.connect().await?;
+    let height = get_election_height(&mut conn).await?;
+    let roots = client
+        .get_cmx_roots(Request::new(VoteRange {
+            start: height,
+            end: height,
+            after: None,
+            domain,
+        }))
+        .await?
+        .into_inner();
+    if !roots.roots.iter().any(|r| r.root == ballot.data.anchors.cmx) {
+        anyhow::bail!("The vote chain does not have the cmx anchor of height {height}");
+    }
+    let txid = ballot.data.sighash()?;
+    let pow_bits = election.pow_bits;
+    if pow_bits > MAX_POW_BITS {
+        anyhow::bail!("The election requires a stamp of {pow_bits} bits, above {MAX_POW_BITS}");
+    }
+    let sighash = txid.clone();
+    let pow_nonce =
+        tokio::task::spawn_blocking(move || mint_pow_stamp(&sighash, pow_bits))
+            .await?;
     client
         .submit_vote(Request::new(crate::vote_rpc::Ballot {
             ballot: ballot_bytes,
+            pow_nonce,
             ..Default::default()
         }))
         .await?;
-    let txid = ballot.data.sighash()?;
     Ok(txid)
 }
 
@@ -231,7 +310,11 @@ async fn connect_to_vote_server(context: &Context) -> Result<VoteClient> {
 async fn download_election(url: &str) -> Result<ElectionPropsPub> {
     let ep = Endpoint::from_shared(url.to_string())?;
     let mut client = VoteStreamerClient::connect(ep).await?;
-    let election = client.get_election(Request::new(Empty {})).await?.into_inner();
+    // Servers with several elections require the domain
+    let election = client
+        .get_election(Request::new(ElectionId::default()))
+        .await?
+        .into_inner();
     let election: ElectionPropsPub = serde_json::from_str(&election.election)?;
     Ok(election)
 }
@@ -0,0 +1,139 @@
+use anyhow::anyhow;
+use blake2b_simd::Params;
+use orchard::{
+    keys::{SpendAuthorizingKey, SpendingKey},
+    primitives::redpallas::{Signature, SigningKey, SpendAuth, VerificationKey},
+};
+use pasta_curves::pallas;
+use rand_core::OsRng;
+use zcash_protocol::consensus::Network;
+
+use crate::{ZCVResult, db::derive_spending_key, error::IntoAnyhow, tiu};
+
+pub const ZCV_ADMIN_PERSONAL: &[u8] = b"ZCVote_AdminMsg_";
+
+// The election authority signs with the spend authorizing key
+// of the account that receives the ballots
+pub fn authority_signing_key(sk: &SpendingKey) -> SigningKey<SpendAuth> {
+    let ask = SpendAuthorizingKey::from(sk);
+    ask.randomize(&pallas::Scalar::zero())
+}
+
+pub fn authority_key(sk: &SpendingKey) -> [u8; 32] {
+    let vk = VerificationKey::from(&authority_signing_key(sk));
+    (&vk).into()
+}
+
+pub fn authority_key_from_seed(network: &Network, seed: &str) -> ZCVResult<[u8; 32]> {
+    let sk = derive_spending_key(network, seed, 0)?;
+    Ok(authority_key(&sk))
+}
+
+// Where an admin message can be applied: the vote chain and the
+// election it acts on. The domain is empty for the messages
+// that act on the whole chain (validators and authority)
+pub struct AdminScope<'a> {
+    pub chain_id: &'a str,
+    pub domain: &'a [u8],
+}
+
+fn admin_sighash(scope: &AdminScope, nonce: u64, message: &[u8]) -> [u8; 32] {
+    let hash = Params::new()
+        .personal(ZCV_ADMIN_PERSONAL)
+        .hash_length(32)
+        .to_state()
+        .update(&(scope.chain_id.len() as u32).to_le_bytes())
+        .update(scope.chain_id.as_bytes())
+        .update(&(scope.domain.len() as u32).to_le_bytes())
+        .update(scope.domain)
+        .update(&nonce.to_le_bytes())
+        .update(message)
+        .finalize();
+    tiu!(hash.as_bytes())
+}
+
+pub fn sign_admin_message(
+    network: &Network,
+    seed: &str,
+    scope: &AdminScope,
+    nonce: u64,
+    message: &[u8],
+) -> ZCVResult<[u8; 64]> {
+    let sk = derive_spending_key(network, seed, 0)?;
+    let signing_key = authority_signing_key(&sk);
+    let signature = signing_key.sign(OsRng, &admin_sighash(scope, nonce, message));
+    Ok((&signature).into())
+}
+
+pub fn check_authority_key(authority: &[u8]) -> ZCVResult<[u8; 32]> {
+    let authority: [u8; 32] = authority
+        .try_into()
+        .map_err(|_| anyhow!("Invalid authority key"))?;
+    VerificationKey::<SpendAuth>::try_from(authority).anyhow()?;
+    Ok(authority)
+}
+
+pub fn verify_admin_message(
+    authority: &[u8],
+    scope: &AdminScope,
+    nonce: u64,
+    message: &[u8],
+    signature: &[u8],
+) -> ZCVResult<()> {
+    let authority = check_authority_key(authority)?;
+    let vk = VerificationKey::<SpendAuth>::try_from(authority).anyhow()?;
+    let signature: [u8; 64] = signature
+        .try_into()
+        .map_err(|_| anyhow!("Invalid admin signature"))?;
+    let signature = Signature::<SpendAuth>::from(signature);
+    vk.verify(&admin_sighash(scope, nonce, message), &signature)
+        .map_err(|_| anyhow!("Admin message not signed by the election authority"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use zcash_protocol::consensus::Network;
+
+    use crate::{
+        authority::{
+            AdminScope, authority_key_from_seed, sign_admin_message, verify_admin_message,
+        },
+        tests::{TEST_ELECTION_SEED, TEST_SEED},
+    };
+
+    #[test]
+    fn test_admin_signature() {
+        let network = Network::MainNetwork;
+        let authority = authority_key_from_seed(&network, TEST_ELECTION_SEED).unwrap();
+        let scope = AdminScope {
+            chain_id: "zcv-test",
+            domain: &[1u8; 32],
+        };
+        let message = b"lock";
+        let signature =
+            sign_admin_message(&network, TEST_ELECTION_SEED, &scope, 1, message).unwrap();
+        assert!(verify_admin_message(&authority, &scope, 1, message, &signature).is_ok());
+        // Replayed with another nonce
+        assert!(verify_admin_message(&authority, &scope, 2, message, &signature).is_err());
+        // Replayed on another chain or for another election
+        let other_chain = AdminScope {
+            chain_id: "zcv-other",
+            ..scope
+        };
+        assert!(verify_admin_message(&authority, &other_chain, 1, message, &signature).is_err());
+        let other_election = AdminScope {
+            domain: &[2u8; 32],
+            ..scope
+        };
+        assert!(verify_admin_message(&authority, &other_election, 1, message, &signature).is_err());
+        // Signed by someone else
+        let signature = sign_admin_message(&network, TEST_SEED, &scope, 1, message).unwrap();
+        assert!(verify_admin_message(&authority, &scope, 1, message, &signature).is_err());
+        // Keys of another network
+        let authority = authority_key_from_seed(&Network::TestNetwork, TEST_ELECTION_SEED).unwrap();
+        let signature =
+            sign_admin_message(&network, TEST_ELECTION_SEED, &scope, 1, message).unwrap();
+        assert!(verify_admin_message(&authority, &scope, 1, message, &signature).is_err());
+    }
+}
@@ -3,7 +3,7 @@
 pub struct Empty {}
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct VoteMessage {
-    #[prost(oneof = "vote_message::TypeOneof", tags = "1, 2, 3, 4")]
+    #[prost(oneof = "vote_message::TypeOneof", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
     pub type_oneof: ::core::option::Option<vote_message::TypeOneof>,
 }
 /// Nested message and enum types in `VoteMessage`.
@@ -14,12 +14,31 @@ pub mod vote_message {
         AddValidator(super::Validator),
         #[prost(message, tag = "2")]
         SetElection(super::Election),
+        /// Locks the election, see ElectionId
         #[prost(message, tag = "3")]
-        Lock(super::Empty),
+        Lock(super::ElectionId),
         #[prost(message, tag = "4")]
         Ballot(super::Ballot),
+        #[prost(message, tag = "5")]
+        Signed(super::SignedMessage),
+        #[prost(message, tag = "6")]
+        RemoveValidator(super::Validator),
+        #[prost(message, tag = "7")]
+        SetValidatorPower(super::Validator),
+        #[prost(message, tag = "8")]
+        RotateAuthority(super::Authority),
     }
 }
+/// Admin VoteMessage signed by the election authority
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SignedMessage {
+    #[prost(bytes = "vec", tag = "1")]
+    pub message: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub nonce: u64,
+    #[prost(bytes = "vec", tag = "3")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
+}
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct Validator {
     #[prost(bytes = "vec", tag = "1")]
@@ -27,6 +46,12 @@ pub struct Validator {
     #[prost(uint32, tag = "2")]
     pub power: u32,
 }
+/// Replaces the key that signs the admin messages
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct Authority {
+    #[prost(bytes = "vec", tag = "1")]
+    pub pub_key: ::prost::alloc::vec::Vec<u8>,
+}
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct Election {
     #[prost(string, tag = "1")]
@@ -36,19 +61,94 @@ pub struct Election {
     #[prost(bytes = "vec", tag = "3")]
     pub cmx_tree_state: ::prost::alloc::vec::Vec<u8>,
 }
+/// Selects an election of the vote chain by its domain.
+/// It can be left empty if there is a single election
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ElectionId {
+    #[prost(bytes = "vec", tag = "1")]
+    pub domain: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Elections {
+    #[prost(message, repeated, tag = "1")]
+    pub elections: ::prost::alloc::vec::Vec<Election>,
+}
+/// Last vote height of the election with ballots
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct VoteHeight {
     #[prost(uint32, tag = "1")]
     pub height: u32,
+    /// CometBFT hash of the latest block
     #[prost(bytes = "vec", tag = "2")]
     pub hash: ::prost::alloc::vec::Vec<u8>,
+    /// Root of the cmx tree at height
+    #[prost(bytes = "vec", tag = "3")]
+    pub cmx_root: ::prost::alloc::vec::Vec<u8>,
+    /// Vote chain height of the block of hash. It is not the block of
+    /// height, which is the end of the election + its block height
+    #[prost(uint32, tag = "4")]
+    pub block_height: u32,
 }
-#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+/// Root of the cmx tree since the vote height
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct CmxRoot {
+    #[prost(uint32, tag = "1")]
+    pub height: u32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub root: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CmxRoots {
+    #[prost(message, repeated, tag = "1")]
+    pub roots: ::prost::alloc::vec::Vec<CmxRoot>,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct VoteRange {
     #[prost(uint32, tag = "1")]
     pub start: u32,
     #[prost(uint32, tag = "2")]
     pub end: u32,
+    /// Resume an interrupted stream after this ballot
+    #[prost(message, optional, tag = "3")]
+    pub after: ::core::option::Option<VoteCursor>,
+    /// Election, see ElectionId
+    #[prost(bytes = "vec", tag = "4")]
+    pub domain: ::prost::alloc::vec::Vec<u8>,
+}
+/// Position of a ballot in the vote chain
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct VoteCursor {
+    #[prost(uint32, tag = "1")]
+    pub height: u32,
+    #[prost(uint32, tag = "2")]
+    pub itx: u32,
+}
+/// A stream of GetVoteRange is complete only if it ends with
+/// the trailer. It has the cursor of the last ballot of the range
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct VoteRangeItem {
+    #[prost(oneof = "vote_range_item::Item", tags = "1, 2")]
+    pub item: ::core::option::Option<vote_range_item::Item>,
+}
+/// Nested message and enum types in `VoteRangeItem`.
+pub mod vote_range_item {
+    #[derive(Clone, PartialEq, Eq, Hash, ::prost::Oneof)]
+    pub enum Item {
+        #[prost(message, tag = "1")]
+        Ballot(super::Ballot),
+        #[prost(message, tag = "2")]
+        End(super::VoteCursor),
+    }
+}
+/// Ballots from the vote height start, then the new ones
+/// as they are finalized
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct VoteSubscription {
+    #[prost(uint32, tag = "1")]
+    pub start: u32,
+    /// Election, see ElectionId
+    #[prost(bytes = "vec", tag = "2")]
+    pub domain: ::prost::alloc::vec::Vec<u8>,
 }
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct Ballot {
@@ -58,12 +158,115 @@ pub struct Ballot {
     pub itx: u32,
     #[prost(bytes = "vec", tag = "3")]
     pub ballot: ::prost::alloc::vec::Vec<u8>,
+    /// Proof of work stamp, required if the election has pow_bits
+    #[prost(uint64, tag = "4")]
+    pub pow_nonce: u64,
 }
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct Hash {
     #[prost(bytes = "vec", tag = "1")]
     pub hash: ::prost::alloc::vec::Vec<u8>,
 }
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct StatsRequest {
+    /// Vote heights per bucket, 100 if 0
+    #[prost(uint32, tag = "1")]
+    pub bucket_size: u32,
+    /// Election, see ElectionId
+    #[prost(bytes = "vec", tag = "2")]
+    pub domain: ::prost::alloc::vec::Vec<u8>,
+}
+/// Ballots of the vote heights \[start, start + bucket_size)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct HeightBucket {
+    #[prost(uint32, tag = "1")]
+    pub start: u32,
+    #[prost(uint32, tag = "2")]
+    pub ballots: u32,
+}
+/// Turnout of the election. Values and memos are encrypted,
+/// none of this tells how anyone voted
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ElectionStats {
+    #[prost(uint32, tag = "1")]
+    pub ballots: u32,
+    /// Every action consumes one nullifier
+    #[prost(uint32, tag = "2")]
+    pub actions: u32,
+    #[prost(message, repeated, tag = "4")]
+    pub buckets: ::prost::alloc::vec::Vec<HeightBucket>,
+    /// 0 if there are no ballots
+    #[prost(uint32, tag = "5")]
+    pub first_height: u32,
+    #[prost(uint32, tag = "6")]
+    pub last_height: u32,
+    /// Notes of the cmx tree: the Orchard notes at the snapshot
+    /// height and the notes added by the vote chain
+    #[prost(uint32, tag = "7")]
+    pub cmx_tree_size: u32,
+    /// Anchors usable by the ballots
+    #[prost(uint32, tag = "8")]
+    pub cmx_roots: u32,
+}
+/// Status of a ballot submitted to this node
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct BallotStatus {
+    #[prost(enumeration = "ballot_status::Status", tag = "1")]
+    pub status: i32,
+    /// Vote height and index of the ballot if included,
+    /// height of the block that rejected it
+    #[prost(uint32, tag = "2")]
+    pub height: u32,
+    #[prost(uint32, tag = "3")]
+    pub itx: u32,
+    #[prost(string, tag = "4")]
+    pub reason: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `BallotStatus`.
+pub mod ballot_status {
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Status {
+        Unknown = 0,
+        Pending = 1,
+        Included = 2,
+        Rejected = 3,
+    }
+    impl Status {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Self::Unknown => "UNKNOWN",
+                Self::Pending => "PENDING",
+                Self::Included => "INCLUDED",
+                Self::Rejected => "REJECTED",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "UNKNOWN" => Some(Self::Unknown),
+                "PENDING" => Some(Self::Pending),
+                "INCLUDED" => Some(Self::Included),
+                "REJECTED" => Some(Self::Rejected),
+                _ => None,
+            }
+        }
+    }
+}
 /// Generated client implementations.
 pub mod vote_streamer_client {
     #![allow(
@@ -75,6 +278,7 @@ pub mod vote_streamer_client {
     )]
     use tonic::codegen::*;
     use tonic::codegen::http::Uri;
+    /// Public API of the vote chain
     #[derive(Debug, Clone)]
     pub struct VoteStreamerClient<T> {
         inner: tonic::client::Grpc<T>,
@@ -157,7 +361,7 @@ pub mod vote_streamer_client {
         }
         pub async fn get_election(
             &mut self,
-            request: impl tonic::IntoRequest<super::Empty>,
+            request: impl tonic::IntoRequest<super::ElectionId>,
         ) -> std::result::Result<tonic::Response<super::Election>, tonic::Status> {
             self.inner
                 .ready()
@@ -178,10 +382,10 @@ pub mod vote_streamer_client {
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn set_election(
+        pub async fn list_elections(
             &mut self,
-            request: impl tonic::IntoRequest<super::Election>,
-        ) -> std::result::Result<tonic::Response<super::Hash>, tonic::Status> {
+            request: impl tonic::IntoRequest<super::Empty>,
+        ) -> std::result::Result<tonic::Response<super::Elections>, tonic::Status> {
             self.inner
                 .ready()
                 .await
@@ -192,19 +396,19 @@ pub mod vote_streamer_client {
                 })?;
             let codec = tonic_prost::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/cash.z.vote.sdk.rpc.VoteStreamer/SetElection",
+                "/cash.z.vote.sdk.rpc.VoteStreamer/ListElections",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
-                    GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "SetElection"),
+                    GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "ListElections"),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn add_validator(
+        pub async fn get_latest_vote_height(
             &mut self,
-            request: impl tonic::IntoRequest<super::Validator>,
-        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status> {
+            request: impl tonic::IntoRequest<super::ElectionId>,
+        ) -> std::result::Result<tonic::Response<super::VoteHeight>, tonic::Status> {
             self.inner
                 .ready()
                 .await
@@ -215,19 +419,48 @@ pub mod vote_streamer_client {
                 })?;
             let codec = tonic_prost::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/cash.z.vote.sdk.rpc.VoteStreamer/AddValidator",
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetLatestVoteHeight",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
-                    GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "AddValidator"),
+                    GrpcMethod::new(
+                        "cash.z.vote.sdk.rpc.VoteStreamer",
+                        "GetLatestVoteHeight",
+                    ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn lock(
+        pub async fn get_vote_range(
             &mut self,
-            request: impl tonic::IntoRequest<super::Empty>,
-        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status> {
+            request: impl tonic::IntoRequest<super::VoteRange>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::VoteRangeItem>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetVoteRange",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "GetVoteRange"),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+        pub async fn submit_vote(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Ballot>,
+        ) -> std::result::Result<tonic::Response<super::Hash>, tonic::Status> {
             self.inner
                 .ready()
                 .await
@@ -238,17 +471,19 @@ pub mod vote_streamer_client {
                 })?;
             let codec = tonic_prost::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/cash.z.vote.sdk.rpc.VoteStreamer/Lock",
+                "/cash.z.vote.sdk.rpc.VoteStreamer/SubmitVote",
             );
             let mut req = request.into_request();
             req.extensions_mut()
-                .insert(GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "Lock"));
+                .insert(
+                    GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "SubmitVote"),
+                );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn get_latest_vote_height(
+        pub async fn get_ballot_status(
             &mut self,
-            request: impl tonic::IntoRequest<super::Empty>,
-        ) -> std::result::Result<tonic::Response<super::VoteHeight>, tonic::Status> {
+            request: impl tonic::IntoRequest<super::Hash>,
+        ) -> std::result::Result<tonic::Response<super::BallotStatus>, tonic::Status> {
             self.inner
                 .ready()
                 .await
@@ -259,21 +494,21 @@ pub mod vote_streamer_client {
                 })?;
             let codec = tonic_prost::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/cash.z.vote.sdk.rpc.VoteStreamer/GetLatestVoteHeight",
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetBallotStatus",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
                     GrpcMethod::new(
                         "cash.z.vote.sdk.rpc.VoteStreamer",
-                        "GetLatestVoteHeight",
+                        "GetBallotStatus",
                     ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn get_vote_range(
+        pub async fn subscribe_votes(
             &mut self,
-            request: impl tonic::IntoRequest<super::VoteRange>,
+            request: impl tonic::IntoRequest<super::VoteSubscription>,
         ) -> std::result::Result<
             tonic::Response<tonic::codec::Streaming<super::Ballot>>,
             tonic::Status,
@@ -288,19 +523,19 @@ pub mod vote_streamer_client {
                 })?;
             let codec = tonic_prost::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/cash.z.vote.sdk.rpc.VoteStreamer/GetVoteRange",
+                "/cash.z.vote.sdk.rpc.VoteStreamer/SubscribeVotes",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
-                    GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "GetVoteRange"),
+                    GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "SubscribeVotes"),
                 );
             self.inner.server_streaming(req, path, codec).await
         }
-        pub async fn submit_vote(
+        pub async fn get_cmx_roots(
             &mut self,
-            request: impl tonic::IntoRequest<super::Ballot>,
-        ) -> std::result::Result<tonic::Response<super::Hash>, tonic::Status> {
+            request: impl tonic::IntoRequest<super::VoteRange>,
+        ) -> std::result::Result<tonic::Response<super::CmxRoots>, tonic::Status> {
             self.inner
                 .ready()
                 .await
@@ -311,12 +546,38 @@ pub mod vote_streamer_client {
                 })?;
             let codec = tonic_prost::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/cash.z.vote.sdk.rpc.VoteStreamer/SubmitVote",
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetCmxRoots",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
-                    GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "SubmitVote"),
+                    GrpcMethod::new("cash.z.vote.sdk.rpc.VoteStreamer", "GetCmxRoots"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_election_stats(
+            &mut self,
+            request: impl tonic::IntoRequest<super::StatsRequest>,
+        ) -> std::result::Result<tonic::Response<super::ElectionStats>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetElectionStats",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "cash.z.vote.sdk.rpc.VoteStreamer",
+                        "GetElectionStats",
+                    ),
                 );
             self.inner.unary(req, path, codec).await
         }
@@ -337,27 +598,19 @@ pub mod vote_streamer_server {
     pub trait VoteStreamer: std::marker::Send + std::marker::Sync + 'static {
         async fn get_election(
             &self,
-            request: tonic::Request<super::Empty>,
+            request: tonic::Request<super::ElectionId>,
         ) -> std::result::Result<tonic::Response<super::Election>, tonic::Status>;
-        async fn set_election(
-            &self,
-            request: tonic::Request<super::Election>,
-        ) -> std::result::Result<tonic::Response<super::Hash>, tonic::Status>;
-        async fn add_validator(
-            &self,
-            request: tonic::Request<super::Validator>,
-        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status>;
-        async fn lock(
+        async fn list_elections(
             &self,
             request: tonic::Request<super::Empty>,
-        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status>;
+        ) -> std::result::Result<tonic::Response<super::Elections>, tonic::Status>;
         async fn get_latest_vote_height(
             &self,
-            request: tonic::Request<super::Empty>,
+            request: tonic::Request<super::ElectionId>,
         ) -> std::result::Result<tonic::Response<super::VoteHeight>, tonic::Status>;
         /// Server streaming response type for the GetVoteRange method.
         type GetVoteRangeStream: tonic::codegen::tokio_stream::Stream<
-                Item = std::result::Result<super::Ballot, tonic::Status>,
+                Item = std::result::Result<super::VoteRangeItem, tonic::Status>,
             >
             + std::marker::Send
             + 'static;
@@ -372,7 +625,33 @@ pub mod vote_streamer_server {
             &self,
             request: tonic::Request<super::Ballot>,
         ) -> std::result::Result<tonic::Response<super::Hash>, tonic::Status>;
+        async fn get_ballot_status(
+            &self,
+            request: tonic::Request<super::Hash>,
+        ) -> std::result::Result<tonic::Response<super::BallotStatus>, tonic::Status>;
+        /// Server streaming response type for the SubscribeVotes method.
+        type SubscribeVotesStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::Ballot, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        async fn subscribe_votes(
+            &self,
+            request: tonic::Request<super::VoteSubscription>,
+        ) -> std::result::Result<
+            tonic::Response<Self::SubscribeVotesStream>,
+            tonic::Status,
+        >;
+        async fn get_cmx_roots(
+            &self,
+            request: tonic::Request<super::VoteRange>,
+        ) -> std::result::Result<tonic::Response<super::CmxRoots>, tonic::Status>;
+        async fn get_election_stats(
+            &self,
+            request: tonic::Request<super::StatsRequest>,
+        ) -> std::result::Result<tonic::Response<super::ElectionStats>, tonic::Status>;
     }
+    /// Public API of the vote chain
     #[derive(Debug)]
     pub struct VoteStreamerServer<T> {
         inner: Arc<T>,
@@ -452,7 +731,7 @@ pub mod vote_streamer_server {
                 "/cash.z.vote.sdk.rpc.VoteStreamer/GetElection" => {
                     #[allow(non_camel_case_types)]
                     struct GetElectionSvc<T: VoteStreamer>(pub Arc<T>);
-                    impl<T: VoteStreamer> tonic::server::UnaryService<super::Empty>
+                    impl<T: VoteStreamer> tonic::server::UnaryService<super::ElectionId>
                     for GetElectionSvc<T> {
                         type Response = super::Election;
                         type Future = BoxFuture<
@@ -461,7 +740,7 @@ pub mod vote_streamer_server {
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::Empty>,
+                            request: tonic::Request<super::ElectionId>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
@@ -492,23 +771,23 @@ pub mod vote_streamer_server {
                     };
                     Box::pin(fut)
                 }
-                "/cash.z.vote.sdk.rpc.VoteStreamer/SetElection" => {
+                "/cash.z.vote.sdk.rpc.VoteStreamer/ListElections" => {
                     #[allow(non_camel_case_types)]
-                    struct SetElectionSvc<T: VoteStreamer>(pub Arc<T>);
-                    impl<T: VoteStreamer> tonic::server::UnaryService<super::Election>
-                    for SetElectionSvc<T> {
-                        type Response = super::Hash;
+                    struct ListElectionsSvc<T: VoteStreamer>(pub Arc<T>);
+                    impl<T: VoteStreamer> tonic::server::UnaryService<super::Empty>
+                    for ListElectionsSvc<T> {
+                        type Response = super::Elections;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::Election>,
+                            request: tonic::Request<super::Empty>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as VoteStreamer>::set_election(&inner, request).await
+                                <T as VoteStreamer>::list_elections(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -519,7 +798,7 @@ pub mod vote_streamer_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = SetElectionSvc(inner);
+                        let method = ListElectionsSvc(inner);
                         let codec = tonic_prost::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -535,23 +814,24 @@ pub mod vote_streamer_server {
                     };
                     Box::pin(fut)
                 }
-                "/cash.z.vote.sdk.rpc.VoteStreamer/AddValidator" => {
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetLatestVoteHeight" => {
                     #[allow(non_camel_case_types)]
-                    struct AddValidatorSvc<T: VoteStreamer>(pub Arc<T>);
-                    impl<T: VoteStreamer> tonic::server::UnaryService<super::Validator>
-                    for AddValidatorSvc<T> {
-                        type Response = super::Empty;
+                    struct GetLatestVoteHeightSvc<T: VoteStreamer>(pub Arc<T>);
+                    impl<T: VoteStreamer> tonic::server::UnaryService<super::ElectionId>
+                    for GetLatestVoteHeightSvc<T> {
+                        type Response = super::VoteHeight;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::Validator>,
+                            request: tonic::Request<super::ElectionId>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as VoteStreamer>::add_validator(&inner, request).await
+                                <T as VoteStreamer>::get_latest_vote_height(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -562,7 +842,7 @@ pub mod vote_streamer_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = AddValidatorSvc(inner);
+                        let method = GetLatestVoteHeightSvc(inner);
                         let codec = tonic_prost::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -578,23 +858,69 @@ pub mod vote_streamer_server {
                     };
                     Box::pin(fut)
                 }
-                "/cash.z.vote.sdk.rpc.VoteStreamer/Lock" => {
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetVoteRange" => {
                     #[allow(non_camel_case_types)]
-                    struct LockSvc<T: VoteStreamer>(pub Arc<T>);
-                    impl<T: VoteStreamer> tonic::server::UnaryService<super::Empty>
-                    for LockSvc<T> {
-                        type Response = super::Empty;
+                    struct GetVoteRangeSvc<T: VoteStreamer>(pub Arc<T>);
+                    impl<
+                        T: VoteStreamer,
+                    > tonic::server::ServerStreamingService<super::VoteRange>
+                    for GetVoteRangeSvc<T> {
+                        type Response = super::VoteRangeItem;
+                        type ResponseStream = T::GetVoteRangeStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::VoteRange>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as VoteStreamer>::get_vote_range(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetVoteRangeSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/cash.z.vote.sdk.rpc.VoteStreamer/SubmitVote" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubmitVoteSvc<T: VoteStreamer>(pub Arc<T>);
+                    impl<T: VoteStreamer> tonic::server::UnaryService<super::Ballot>
+                    for SubmitVoteSvc<T> {
+                        type Response = super::Hash;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::Empty>,
+                            request: tonic::Request<super::Ballot>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as VoteStreamer>::lock(&inner, request).await
+                                <T as VoteStreamer>::submit_vote(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -605,7 +931,7 @@ pub mod vote_streamer_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = LockSvc(inner);
+                        let method = SubmitVoteSvc(inner);
                         let codec = tonic_prost::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -621,23 +947,23 @@ pub mod vote_streamer_server {
                     };
                     Box::pin(fut)
                 }
-                "/cash.z.vote.sdk.rpc.VoteStreamer/GetLatestVoteHeight" => {
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetBallotStatus" => {
                     #[allow(non_camel_case_types)]
-                    struct GetLatestVoteHeightSvc<T: VoteStreamer>(pub Arc<T>);
-                    impl<T: VoteStreamer> tonic::server::UnaryService<super::Empty>
-                    for GetLatestVoteHeightSvc<T> {
-                        type Response = super::VoteHeight;
+                    struct GetBallotStatusSvc<T: VoteStreamer>(pub Arc<T>);
+                    impl<T: VoteStreamer> tonic::server::UnaryService<super::Hash>
+                    for GetBallotStatusSvc<T> {
+                        type Response = super::BallotStatus;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::Empty>,
+                            request: tonic::Request<super::Hash>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as VoteStreamer>::get_latest_vote_height(&inner, request)
+                                <T as VoteStreamer>::get_ballot_status(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -649,7 +975,7 @@ pub mod vote_streamer_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = GetLatestVoteHeightSvc(inner);
+                        let method = GetBallotStatusSvc(inner);
                         let codec = tonic_prost::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -665,26 +991,26 @@ pub mod vote_streamer_server {
                     };
                     Box::pin(fut)
                 }
-                "/cash.z.vote.sdk.rpc.VoteStreamer/GetVoteRange" => {
+                "/cash.z.vote.sdk.rpc.VoteStreamer/SubscribeVotes" => {
                     #[allow(non_camel_case_types)]
-                    struct GetVoteRangeSvc<T: VoteStreamer>(pub Arc<T>);
+                    struct SubscribeVotesSvc<T: VoteStreamer>(pub Arc<T>);
                     impl<
                         T: VoteStreamer,
-                    > tonic::server::ServerStreamingService<super::VoteRange>
-                    for GetVoteRangeSvc<T> {
+                    > tonic::server::ServerStreamingService<super::VoteSubscription>
+                    for SubscribeVotesSvc<T> {
                         type Response = super::Ballot;
-                        type ResponseStream = T::GetVoteRangeStream;
+                        type ResponseStream = T::SubscribeVotesStream;
                         type Future = BoxFuture<
                             tonic::Response<Self::ResponseStream>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::VoteRange>,
+                            request: tonic::Request<super::VoteSubscription>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as VoteStreamer>::get_vote_range(&inner, request).await
+                                <T as VoteStreamer>::subscribe_votes(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -695,7 +1021,7 @@ pub mod vote_streamer_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = GetVoteRangeSvc(inner);
+                        let method = SubscribeVotesSvc(inner);
                         let codec = tonic_prost::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -711,23 +1037,23 @@ pub mod vote_streamer_server {
                     };
                     Box::pin(fut)
                 }
-                "/cash.z.vote.sdk.rpc.VoteStreamer/SubmitVote" => {
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetCmxRoots" => {
                     #[allow(non_camel_case_types)]
-                    struct SubmitVoteSvc<T: VoteStreamer>(pub Arc<T>);
-                    impl<T: VoteStreamer> tonic::server::UnaryService<super::Ballot>
-                    for SubmitVoteSvc<T> {
-                        type Response = super::Hash;
+                    struct GetCmxRootsSvc<T: VoteStreamer>(pub Arc<T>);
+                    impl<T: VoteStreamer> tonic::server::UnaryService<super::VoteRange>
+                    for GetCmxRootsSvc<T> {
+                        type Response = super::CmxRoots;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::Ballot>,
+                            request: tonic::Request<super::VoteRange>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as VoteStreamer>::submit_vote(&inner, request).await
+                                <T as VoteStreamer>::get_cmx_roots(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -738,7 +1064,7 @@ pub mod vote_streamer_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = SubmitVoteSvc(inner);
+                        let method = GetCmxRootsSvc(inner);
                         let codec = tonic_prost::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -754,7 +1080,53 @@ pub mod vote_streamer_server {
                     };
                     Box::pin(fut)
                 }
-                _ => {
+                "/cash.z.vote.sdk.rpc.VoteStreamer/GetElectionStats" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetElectionStatsSvc<T: VoteStreamer>(pub Arc<T>);
+                    impl<
+                        T: VoteStreamer,
+                    > tonic::server::UnaryService<super::StatsRequest>
+                    for GetElectionStatsSvc<T> {
+                        type Response = super::ElectionStats;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::StatsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as VoteStreamer>::get_election_stats(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetElectionStatsSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
                     Box::pin(async move {
                         let mut response = http::Response::new(
                             tonic::body::Body::default(),
@@ -794,3 +1166,302 @@ pub mod vote_streamer_server {
         const NAME: &'static str = SERVICE_NAME;
     }
 }
+/// Generated client implementations.
+pub mod admin_service_client {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// Relays the admin messages signed by the operator with the `admin` tool.
+    /// The nodes never hold the authority seed.
+    /// Served on a separate address and protected by a bearer token
+    #[derive(Debug, Clone)]
+    pub struct AdminServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl AdminServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> AdminServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::Body>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> AdminServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::Body>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::Body>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::Body>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
+        {
+            AdminServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        pub async fn submit(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SignedMessage>,
+        ) -> std::result::Result<tonic::Response<super::Hash>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cash.z.vote.sdk.rpc.AdminService/Submit",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("cash.z.vote.sdk.rpc.AdminService", "Submit"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod admin_service_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with AdminServiceServer.
+    #[async_trait]
+    pub trait AdminService: std::marker::Send + std::marker::Sync + 'static {
+        async fn submit(
+            &self,
+            request: tonic::Request<super::SignedMessage>,
+        ) -> std::result::Result<tonic::Response<super::Hash>, tonic::Status>;
+    }
+    /// Relays the admin messages signed by the operator with the `admin` tool.
+    /// The nodes never hold the authority seed.
+    /// Served on a separate address and protected by a bearer token
+    #[derive(Debug)]
+    pub struct AdminServiceServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> AdminServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for AdminServiceServer<T>
+    where
+        T: AdminService,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::Body>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/cash.z.vote.sdk.rpc.AdminService/Submit" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubmitSvc<T: AdminService>(pub Arc<T>);
+                    impl<
+                        T: AdminService,
+                    > tonic::server::UnaryService<super::SignedMessage>
+                    for SubmitSvc<T> {
+                        type Response = super::Hash;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SignedMessage>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AdminService>::submit(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SubmitSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(
+                            tonic::body::Body::default(),
+                        );
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
+            }
+        }
+    }
+    impl<T> Clone for AdminServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "cash.z.vote.sdk.rpc.AdminService";
+    impl<T> tonic::server::NamedService for AdminServiceServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
@@ -1,21 +1,33 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::Parser;
 use figment::{
     Figment,
     providers::{Format, Serialized, Toml},
 };
 use serde::{Deserialize, Serialize};
-use tokio::{sync::Mutex, task::LocalSet};
+use tokio::{
+    sync::{Mutex, watch},
+    task::LocalSet,
+};
 use tonic::transport::Server;
+use tonic_web::GrpcWebLayer;
 use zcvlib::{
-    ZCVError,
-    context::BFTContext,
+    VOTE_FILE_DESCRIPTOR_SET,
+    authority::check_authority_key,
+    context::{AdmissionPolicy, BFTContext},
     db::create_schema,
-    server::{rpc::ZCVServer, run_cometbft_app},
+    server::{
+        admin::{ZCVAdminServer, admin_auth},
+        health::report_health,
+        metrics::serve_metrics,
+        rest::{cors_layer, serve_rest},
+        rpc::ZCVServer,
+        run_cometbft_app,
+    },
     vote::VK,
-    vote_rpc::vote_streamer_server::VoteStreamerServer,
+    vote_rpc::{admin_service_server::AdminServiceServer, vote_streamer_server::VoteStreamerServer},
 };
 
 #[derive(Parser, Serialize, Deserialize, Debug)]
@@ -26,12 +38,32 @@ pub struct Config {
     pub cometbft_port: Option<u16>,
     #[clap(short = 'g', long, value_parser)]
     pub grpc_port: Option<u16>,
+    #[clap(long, value_parser)]
+    pub admin_port: Option<u16>,
+    #[clap(long, value_parser)]
+    pub admin_bind: Option<String>,
+    #[clap(long, value_parser)]
+    pub admin_token: Option<String>,
+    #[clap(long, value_parser)]
+    pub metrics_port: Option<u16>,
+    #[clap(long, value_parser)]
+    pub rest_port: Option<u16>,
+    #[clap(long, value_parser)]
+    pub cors_origins: Option<Vec<String>>,
     #[clap(short, long, value_parser)]
     pub db_path: Option<String>,
     #[clap(short, long, value_parser)]
     pub lwd_url: Option<String>,
     #[clap(short, long)]
     pub unsafe_skip_validation: bool,
+    #[clap(long, value_parser)]
+    pub authority: Option<String>,
+    #[clap(long, value_parser)]
+    pub max_ballot_actions: Option<usize>,
+    #[clap(long, value_parser)]
+    pub max_ballot_size: Option<usize>,
+    #[clap(long, value_parser)]
+    pub submit_rate: Option<u32>,
 }
 
 #[tokio::main]
@@ -46,13 +78,34 @@ pub async fn main() -> Result<()> {
         cometrpc_port,
         cometbft_port,
         grpc_port,
+        admin_port,
+        admin_bind,
+        admin_token,
+        metrics_port,
+        rest_port,
+        cors_origins,
         db_path,
         lwd_url,
         unsafe_skip_validation,
+        authority,
+        max_ballot_actions,
+        max_ballot_size,
+        submit_rate,
     } = config;
     let cometrpc_port = cometrpc_port.unwrap_or(26657);
     let cometbft_port = cometbft_port.unwrap_or(26658);
     let grpc_port = grpc_port.unwrap_or(9010);
+    let admin_port = admin_port.unwrap_or(9011);
+    let admin_addr: SocketAddr = format!(
+        "{}:{admin_port}",
+        admin_bind.as_deref().unwrap_or("127.0.0.1")
+    )
+    .parse()?;
+    // Anyone who reaches the admin service can push admin txs to the mempool
+    if admin_token.is_none() && !admin_addr.ip().is_loopback() {
+        bail!("admin_token is required to serve the admin service on {admin_addr}");
+    }
+    let cors = cors_layer(&cors_origins.unwrap_or_default())?;
     let db_path = db_path.unwrap_or("vote.db".to_string());
     let lwd_url = lwd_url.unwrap_or("https://zec.rocks".to_string());
 
@@ -60,13 +113,23 @@ pub async fn main() -> Result<()> {
     std::sync::LazyLock::force(&VK);
     tracing::info!("Done.");
 
-    let context = BFTContext::new(
+    let mut context = BFTContext::new(
         &db_path,
         &lwd_url,
         cometrpc_port,
         unsafe_skip_validation,
     )
     .await?;
+    if let Some(authority) = authority {
+        context.authority = Some(check_authority_key(&hex::decode(authority)?)?);
+    }
+    let admission = AdmissionPolicy::default();
+    context.admission = AdmissionPolicy {
+        max_actions: max_ballot_actions.unwrap_or(admission.max_actions),
+        max_ballot_size: max_ballot_size.unwrap_or(admission.max_ballot_size),
+        submit_rate: submit_rate.unwrap_or(admission.submit_rate),
+    };
+    let submit_rate = context.admission.submit_rate;
     {
         let mut conn = context.connect().await?;
         create_schema(&mut conn).await?;
@@ -75,14 +138,28 @@ pub async fn main() -> Result<()> {
     let context = Arc::new(Mutex::new(context));
     let context2 = context.clone();
 
-    std::thread::spawn(move || {
+    // SIGTERM or Ctrl-C stop the gRPC servers, then the ABCI app
+    // once the block in progress is committed
+    let (shutdown_tx, shutdown) = watch::channel(false);
+    let shutdown_tx = Arc::new(shutdown_tx);
+    let shutdown2 = shutdown.clone();
+    {
+        let shutdown_tx = shutdown_tx.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            tracing::info!("Shutdown requested");
+            shutdown_tx.send_replace(true);
+        });
+    }
+
+    let abci = std::thread::spawn(move || {
         let r = tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()
             .unwrap();
         r.block_on(async move {
-            run_cometbft_app(context, cometbft_port).await.unwrap();
-            Ok::<_, ZCVError>(())
+            run_cometbft_app(context, cometbft_port, stopped(shutdown)).await?;
+            Ok::<_, anyhow::Error>(())
         })
     });
 
@@ -95,13 +172,62 @@ pub async fn main() -> Result<()> {
             let local = LocalSet::new();
             local
                 .run_until(async move {
-                    let service = ZCVServer { context: context2 };
+                    // Prometheus endpoint, disabled by default
+                    if let Some(port) = metrics_port {
+                        let addr = format!("0.0.0.0:{port}").parse().unwrap();
+                        tokio::spawn(async move {
+                            if let Err(e) = serve_metrics(addr).await {
+                                tracing::warn!("Metrics server failed: {e}");
+                            }
+                        });
+                    }
+                    let (health_reporter, health_service) = tonic_health::server::health_reporter();
+                    tokio::spawn(report_health(context2.clone(), health_reporter));
+                    let reflection = tonic_reflection::server::Builder::configure()
+                        .register_encoded_file_descriptor_set(VOTE_FILE_DESCRIPTOR_SET)
+                        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+                        .build_v1()?;
+                    // Shared by gRPC and REST so that they have the same rate limiter
+                    let service = Arc::new(ZCVServer::new(context2.clone(), submit_rate));
                     let addr = format!("0.0.0.0:{}", grpc_port).parse().unwrap();
-                    let mut builder = Server::builder();
-                    builder
-                        .add_service(VoteStreamerServer::new(service))
-                        .serve(addr)
-                        .await
+                    // gRPC-Web (HTTP/1.1) is served next to gRPC for browsers
+                    let public = Server::builder()
+                        .accept_http1(true)
+                        .layer(cors.clone())
+                        .layer(GrpcWebLayer::new())
+                        .add_service(health_service)
+                        .add_service(reflection)
+                        .add_service(VoteStreamerServer::from_arc(service.clone()))
+                        .serve_with_shutdown(addr, stopped(shutdown2.clone()));
+                    let public = async move {
+                        public.await?;
+                        Ok::<_, anyhow::Error>(())
+                    };
+                    // REST/JSON gateway, disabled by default
+                    let rest = {
+                        let shutdown = stopped(shutdown2.clone());
+                        async move {
+                            if let Some(port) = rest_port {
+                                let addr = format!("0.0.0.0:{port}").parse().unwrap();
+                                serve_rest(service, addr, cors, shutdown).await?;
+                            }
+                            Ok::<_, anyhow::Error>(())
+                        }
+                    };
+                    let admin = AdminServiceServer::with_interceptor(
+                        ZCVAdminServer::new(context2),
+                        admin_auth(admin_token),
+                    );
+                    tracing::info!("Admin service on {admin_addr}");
+                    let admin = Server::builder()
+                        .add_service(admin)
+                        .serve_with_shutdown(admin_addr, stopped(shutdown2));
+                    let admin = async move {
+                        admin.await?;
+                        Ok::<_, anyhow::Error>(())
+                    };
+                    tokio::try_join!(public, rest, admin)?;
+                    Ok::<_, anyhow::Error>(())
                 })
                 .await?;
             Ok::<_, anyhow::Error>(())
@@ -109,6 +235,35 @@ pub async fn main() -> Result<()> {
         Ok::<_, anyhow::Error>(())
     });
 
-    grpc_server.join().unwrap()?;
-    Ok(())
+    let res = grpc_server.join().unwrap();
+    // Also stop the ABCI app if the gRPC servers failed
+    shutdown_tx.send_replace(true);
+    abci.join().unwrap()?;
+    res
+}
+
+async fn stopped(mut shutdown: watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut s) => {
+                s.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
@@ -1,10 +1,16 @@
 
+#[cfg(feature = "server")]
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Result;
-use sqlx::{Sqlite, SqlitePool, pool::PoolConnection, sqlite::SqliteConnectOptions};
+use sqlx::{Sqlite, SqlitePool, pool::PoolConnection, query, sqlite::SqliteConnectOptions};
 
 use crate::db::create_schema;
+#[cfg(feature = "server")]
+use crate::server::status::BallotTracker;
+#[cfg(feature = "server")]
+use tokio::sync::watch;
 
 use crate::ZCVResult;
 
@@ -45,6 +51,35 @@ pub struct BFTContext {
     pub cometrpc_port: u16,
     pub grpc_port: u16,
     pub skip_validation: bool,
+    // Key of the election authority when genesis does not set it
+    pub authority: Option<[u8; 32]>,
+    pub admission: AdmissionPolicy,
+    // Shared by the ABCI app and the gRPC server
+    #[cfg(feature = "server")]
+    pub ballot_tracker: Arc<parking_lot::Mutex<BallotTracker>>,
+    // Height of the last committed block
+    #[cfg(feature = "server")]
+    pub block_notify: Arc<watch::Sender<u32>>,
+}
+
+// Limits on the ballots admitted in the mempool. The chain has no fees
+// so they are checked before the (expensive) proof verification
+#[derive(Clone, Debug)]
+pub struct AdmissionPolicy {
+    pub max_actions: usize,
+    pub max_ballot_size: usize,
+    // Ballots per minute from a given IP to submit_vote, 0 for no limit
+    pub submit_rate: u32,
+}
+
+impl Default for AdmissionPolicy {
+    fn default() -> Self {
+        Self {
+            max_actions: 64,
+            max_ballot_size: 1 << 20,
+            submit_rate: 60,
+        }
+    }
 }
 
 impl BFTContext {
@@ -59,11 +94,22 @@ impl BFTContext {
     pub async fn new(db_path: &str, lwd_url: &str, comet_rpcport: u16, skip_validation: bool) -> ZCVResult<BFTContext> {
         Self::init_logger();
         let context = Context::new(db_path, lwd_url, "").await?;
+        // Snapshots read the state in the background while
+        // the next blocks are committed
+        query("PRAGMA journal_mode = WAL")
+            .execute(&context.pool)
+            .await?;
         Ok(BFTContext {
             context,
             cometrpc_port: comet_rpcport,
             grpc_port: 0,
             skip_validation,
+            authority: None,
+            admission: AdmissionPolicy::default(),
+            #[cfg(feature = "server")]
+            ballot_tracker: Arc::new(parking_lot::Mutex::new(BallotTracker::default())),
+            #[cfg(feature = "server")]
+            block_notify: Arc::new(watch::channel(0).0),
         })
     }
 
@@ -1,5 +1,3 @@
-use std::pin::Pin;
-
 use anyhow::{Context, anyhow};
 use bech32::{Bech32m, Hrp};
 use bincode::config::legacy;
@@ -42,6 +40,22 @@ async fn column_exists(
     Ok(Some(exists))
 }
 
+// Add a column to a table that was created without it
+#[cfg(feature = "server")]
+async fn add_column(
+    conn: &mut SqliteConnection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> ZCVResult<()> {
+    if column_exists(&mut *conn, table, column).await? != Some(true) {
+        query(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"))
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
 pub async fn drop_schema(conn: &mut SqliteConnection) -> ZCVResult<()> {
     for table in &[
         "v_state",
@@ -52,6 +66,9 @@ pub async fn drop_schema(conn: &mut SqliteConnection) -> ZCVResult<()> {
         "v_ballots",
         "v_actions",
         "vs_cmxs",
+        "v_validators",
+        "v_snapshots",
+        "v_snapshot_chunks",
         "v_results",
         "v_final_results",
     ] {
@@ -75,11 +92,22 @@ pub async fn create_schema(conn: &mut SqliteConnection) -> ZCVResult<()> {
     };
 
     // Work around schema change prior to version tag
-    if column_exists(conn, "v_state", "locked").await? == Some(true) {
+    if version == 0 && column_exists(conn, "v_state", "locked").await? == Some(true) {
         version = 1;
     }
 
-    if version != 4 {
+    // The schema is created in a transaction so that an interrupted
+    // migration runs again from version 4
+    let mut db_tx = conn.begin().await?;
+    let conn: &mut SqliteConnection = &mut db_tx;
+
+    // Version 4 is migrated, see migrate_v4
+    #[cfg(feature = "server")]
+    if version == 4 {
+        rename_v4_tables(&mut *conn).await?;
+    }
+
+    if version != 4 && version != 5 {
         drop_schema(&mut *conn).await?;
     }
 
@@ -97,10 +125,32 @@ pub async fn create_schema(conn: &mut SqliteConnection) -> ZCVResult<()> {
 
     query(
         "INSERT INTO v_state(id, version)
-    VALUES (0, 4) ON CONFLICT DO NOTHING",
+    VALUES (0, 5) ON CONFLICT DO NOTHING",
     )
     .execute(&mut *conn)
     .await?;
+
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_state", "block_height", "INTEGER NOT NULL DEFAULT 0").await?;
+
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_state", "apphash", "BLOB NOT NULL DEFAULT X''").await?;
+
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_state", "authority", "BLOB NOT NULL DEFAULT X''").await?;
+
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_state", "admin_nonce", "INTEGER NOT NULL DEFAULT 0").await?;
+
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_state", "max_validators", "INTEGER NOT NULL DEFAULT 0").await?;
+
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_state", "block_hash", "BLOB NOT NULL DEFAULT X''").await?;
+
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_state", "chain_id", "TEXT NOT NULL DEFAULT ''").await?;
+
     query(
         "CREATE TABLE IF NOT EXISTS accounts(
         id_account INTEGER PRIMARY KEY,
@@ -125,6 +175,17 @@ pub async fn create_schema(conn: &mut SqliteConnection) -> ZCVResult<()> {
     .execute(&mut *conn)
     .await?;
 
+    // height and frontier are the last vote height with ballots
+    // and the cmx tree of the elections of the vote chain
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_elections", "height", "INTEGER NOT NULL DEFAULT 0").await?;
+
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_elections", "frontier", "BLOB NOT NULL DEFAULT X''").await?;
+
+    #[cfg(feature = "server")]
+    add_column(&mut *conn, "v_elections", "locked", "BOOL NOT NULL DEFAULT 0").await?;
+
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS v_notes(
         id_note INTEGER PRIMARY KEY,
@@ -164,32 +225,70 @@ pub async fn create_schema(conn: &mut SqliteConnection) -> ZCVResult<()> {
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS v_ballots(
         id_ballot INTEGER PRIMARY KEY,
+        domain BLOB NOT NULL,
         height INTEGER NOT NULL,
         itx INTEGER NOT NULL,
         data BLOB NOT NULL,
         witnesses BLOB NOT NULL,
-        UNIQUE (height, itx))",
+        sighash BLOB,
+        UNIQUE (domain, height, itx))",
     )
     .execute(&mut *conn)
     .await?;
 
+    #[cfg(feature = "server")]
+    query("CREATE INDEX IF NOT EXISTS i_ballots_sighash ON v_ballots(sighash)")
+        .execute(&mut *conn)
+        .await?;
+
     #[cfg(feature = "server")]
     query(
         "CREATE TABLE IF NOT EXISTS vs_cmxs(
-        cmx BLOB PRIMARY KEY NOT NULL)",
+        domain BLOB NOT NULL,
+        cmx BLOB NOT NULL,
+        height INTEGER,
+        PRIMARY KEY (domain, cmx))",
     )
     .execute(&mut *conn)
     .await?;
 
     #[cfg(feature = "server")]
-    let _ = query("ALTER TABLE vs_cmxs ADD COLUMN height INTEGER")
-        .execute(&mut *conn)
-        .await;
+    query(
+        "CREATE TABLE IF NOT EXISTS v_snapshots(
+        height INTEGER PRIMARY KEY NOT NULL,
+        format INTEGER NOT NULL,
+        chunks INTEGER NOT NULL,
+        hash BLOB NOT NULL,
+        metadata BLOB NOT NULL)",
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    #[cfg(feature = "server")]
+    query(
+        "CREATE TABLE IF NOT EXISTS v_snapshot_chunks(
+        height INTEGER NOT NULL,
+        idx INTEGER NOT NULL,
+        data BLOB NOT NULL,
+        PRIMARY KEY (height, idx))",
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    #[cfg(feature = "server")]
+    query(
+        "CREATE TABLE IF NOT EXISTS v_validators(
+        pub_key BLOB PRIMARY KEY NOT NULL,
+        power INTEGER NOT NULL)",
+    )
+    .execute(&mut *conn)
+    .await?;
 
     #[cfg(feature = "server")]
     query(
         "CREATE TABLE IF NOT EXISTS v_actions(
         id_action INTEGER PRIMARY KEY,
+        domain BLOB NOT NULL,
         height INTEGER NOT NULL,
         ballot INTEGER NOT NULL,
         idx INTEGER NOT NULL,
@@ -220,6 +319,86 @@ pub async fn create_schema(conn: &mut SqliteConnection) -> ZCVResult<()> {
     )
     .execute(&mut *conn)
     .await?;
+
+    #[cfg(feature = "server")]
+    if version == 4 {
+        migrate_v4(&mut *conn).await?;
+    }
+    query("UPDATE v_state SET version = 5 WHERE id = 0")
+        .execute(&mut *conn)
+        .await?;
+
+    db_tx.commit().await?;
+    Ok(())
+}
+
+// Version 4 had a single election, id_election 0, whose vote height,
+// cmx tree and lock were in v_state. Its ballots, actions and cmx roots
+// had no domain. These tables are keyed by domain now and SQLite cannot
+// change the keys of a table, so they are set aside and copied into
+// the new tables by migrate_v4
+#[cfg(feature = "server")]
+async fn rename_v4_tables(conn: &mut SqliteConnection) -> ZCVResult<()> {
+    for (table, old) in [
+        ("v_ballots", "v4_ballots"),
+        ("v_actions", "v4_actions"),
+        ("vs_cmxs", "v4_cmxs"),
+    ] {
+        if column_exists(&mut *conn, table, "height").await? == Some(true) {
+            query(&format!("ALTER TABLE {table} RENAME TO {old}"))
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+    // It moved with v_ballots
+    query("DROP INDEX IF EXISTS i_ballots_sighash")
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+async fn migrate_v4(conn: &mut SqliteConnection) -> ZCVResult<()> {
+    if column_exists(&mut *conn, "v_state", "locked").await? == Some(true) {
+        query(
+            "UPDATE v_elections SET height = s.height, frontier = s.frontier, locked = s.locked
+            FROM v_state s WHERE s.id = 0 AND v_elections.id_election = 0",
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    if column_exists(&mut *conn, "v4_ballots", "height").await? == Some(true) {
+        query(
+            "INSERT INTO v_ballots(id_ballot, domain, height, itx, data, witnesses, sighash)
+            SELECT b.id_ballot, e.domain, b.height, b.itx, b.data, b.witnesses, b.sighash
+            FROM v4_ballots b, v_elections e WHERE e.id_election = 0",
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    if column_exists(&mut *conn, "v4_actions", "height").await? == Some(true) {
+        query(
+            "INSERT INTO v_actions(id_action, domain, height, ballot, idx, dnf, cmx)
+            SELECT a.id_action, e.domain, a.height, a.ballot, a.idx, a.dnf, a.cmx
+            FROM v4_actions a, v_elections e WHERE e.id_election = 0",
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    if column_exists(&mut *conn, "v4_cmxs", "height").await? == Some(true) {
+        query(
+            "INSERT INTO vs_cmxs(domain, cmx, height)
+            SELECT e.domain, c.cmx, c.height
+            FROM v4_cmxs c, v_elections e WHERE e.id_election = 0",
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    for table in ["v4_ballots", "v4_actions", "v4_cmxs"] {
+        query(&format!("DROP TABLE IF EXISTS {table}"))
+            .execute(&mut *conn)
+            .await?;
+    }
     Ok(())
 }
 
@@ -283,11 +462,16 @@ pub async fn store_election(
     cmx_tree: &[u8],
 ) -> ZCVResult<()> {
     let json = serde_json::to_string(election).anyhow()?;
+    // A wallet has a single election, it replaces the previous one
+    query("DELETE FROM v_elections WHERE domain <> ?1")
+        .bind(election.domain.as_slice())
+        .execute(&mut *conn)
+        .await
+        .context("store_election:delete")?;
     query(
         "INSERT INTO v_elections
-            (id_election, domain, end, need_sig, name, address, data, nf_root, cmx_tree)
-            VALUES (0, ?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT DO UPDATE SET
-            domain = excluded.domain,
+            (domain, end, need_sig, name, address, data, nf_root, cmx_tree)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT (domain) DO UPDATE SET
             end = excluded.end,
             need_sig = excluded.need_sig,
             name = excluded.name,
@@ -341,7 +525,7 @@ pub async fn client_delete_election_data(
         .await?;
     query("DELETE FROM v_notes").execute(&mut *db_tx).await?;
     query("DELETE FROM v_spends").execute(&mut *db_tx).await?;
-    query("UPDATE v_state SET height = (SELECT start - 1 FROM v_elections WHERE id_election = 0) WHERE id = 0")
+    query("UPDATE v_state SET height = (SELECT start - 1 FROM v_elections LIMIT 1) WHERE id = 0")
         .execute(&mut *db_tx)
         .await?;
 
@@ -386,11 +570,13 @@ pub async fn get_ivks(
     Ok((fvk, ivks.0, ivks.1))
 }
 
+// The election of the wallet. The vote chain has one per domain,
+// see get_vote_election
 pub async fn get_election(
     conn: &mut SqliteConnection,
 ) -> ZCVResult<(ElectionPropsPub, Vec<u8>, Vec<u8>)> {
     let row: Option<(String, Vec<u8>, Vec<u8>)> =
-        query_as("SELECT data, nf_root, cmx_tree FROM v_elections WHERE id_election = 0")
+        query_as("SELECT data, nf_root, cmx_tree FROM v_elections LIMIT 1")
             .fetch_optional(conn)
             .await
             .context("get_election")?;
@@ -401,8 +587,7 @@ pub async fn get_election(
 
 pub async fn get_domain(conn: &mut SqliteConnection) -> ZCVResult<(Fp, String)> {
     let (domain, address): (Vec<u8>, String) = query_as(
-        "SELECT domain, address FROM v_elections
-        WHERE id_election = 0",
+        "SELECT domain, address FROM v_elections LIMIT 1",
     )
     .fetch_one(conn)
     .await
@@ -412,8 +597,14 @@ pub async fn get_domain(conn: &mut SqliteConnection) -> ZCVResult<(Fp, String)>
 }
 
 #[cfg(feature = "server")]
-pub async fn store_cmx_root(conn: &mut SqliteConnection, cmx: &[u8], height: u32) -> ZCVResult<()> {
-    query("INSERT INTO vs_cmxs(cmx, height) VALUES (?1, ?2) ON CONFLICT DO NOTHING")
+pub async fn store_cmx_root(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+    cmx: &[u8],
+    height: u32,
+) -> ZCVResult<()> {
+    query("INSERT INTO vs_cmxs(domain, cmx, height) VALUES (?1, ?2, ?3) ON CONFLICT DO NOTHING")
+        .bind(domain)
         .bind(cmx)
         .bind(height)
         .execute(conn)
@@ -422,8 +613,13 @@ pub async fn store_cmx_root(conn: &mut SqliteConnection, cmx: &[u8], height: u32
 }
 
 #[cfg(feature = "server")]
-pub async fn check_cmx_root(conn: &mut SqliteConnection, cmx_root: &[u8]) -> ZCVResult<()> {
-    let exist: Option<(bool,)> = query_as("SELECT 1 FROM vs_cmxs WHERE cmx = ?1")
+pub async fn check_cmx_root(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+    cmx_root: &[u8],
+) -> ZCVResult<()> {
+    let exist: Option<(bool,)> = query_as("SELECT 1 FROM vs_cmxs WHERE domain = ?1 AND cmx = ?2")
+        .bind(domain)
         .bind(cmx_root)
         .fetch_optional(conn)
         .await?;
@@ -450,6 +646,236 @@ pub async fn get_election_height(conn: &mut SqliteConnection) -> ZCVResult<u32>
     Ok(height)
 }
 
+// Elections of the vote chain, keyed by domain. Unlike the wallet,
+// the vote chain keeps the other elections. The cmx tree starts
+// from cmx_tree and the vote height from the end of the election
+#[cfg(feature = "server")]
+pub async fn store_vote_election(
+    conn: &mut SqliteConnection,
+    election: &ElectionPropsPub,
+    nf_root: &[u8],
+    cmx_tree: &[u8],
+) -> ZCVResult<()> {
+    let json = serde_json::to_string(election).anyhow()?;
+    query(
+        "INSERT INTO v_elections
+            (domain, end, need_sig, name, address, data, nf_root, cmx_tree, height, frontier)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?2, ?8)",
+    )
+    .bind(election.domain.as_slice())
+    .bind(election.end)
+    .bind(election.need_sig)
+    .bind(&election.name)
+    .bind(&election.address)
+    .bind(&json)
+    .bind(nf_root)
+    .bind(cmx_tree)
+    .execute(&mut *conn)
+    .await
+    .context("store vote election")?;
+    Ok(())
+}
+
+// Elections of the vote chain with their nf root, current
+// cmx tree and lock, in the order they were set
+#[cfg(feature = "server")]
+pub async fn list_vote_elections(
+    conn: &mut SqliteConnection,
+) -> ZCVResult<Vec<(ElectionPropsPub, Vec<u8>, Vec<u8>, bool)>> {
+    let rows: Vec<(String, Vec<u8>, Vec<u8>, bool)> = query_as(
+        "SELECT data, nf_root, frontier, locked FROM v_elections ORDER BY id_election",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .context("list vote elections")?;
+    let mut elections = vec![];
+    for (data, nf_root, frontier, locked) in rows {
+        let e = serde_json::from_str::<ElectionPropsPub>(&data)?;
+        elections.push((e, nf_root, frontier, locked));
+    }
+    Ok(elections)
+}
+
+// Domain of the election selected by a request. An empty domain
+// selects the election of the vote chain if there is only one
+#[cfg(feature = "server")]
+pub async fn resolve_domain(conn: &mut SqliteConnection, domain: &[u8]) -> ZCVResult<Vec<u8>> {
+    if !domain.is_empty() {
+        let r: Option<(Vec<u8>,)> = query_as("SELECT domain FROM v_elections WHERE domain = ?1")
+            .bind(domain)
+            .fetch_optional(&mut *conn)
+            .await
+            .context("resolve domain")?;
+        let (domain,) = r.ok_or(anyhow!("Unknown election"))?;
+        return Ok(domain);
+    }
+    let rows: Vec<(Vec<u8>,)> = query_as("SELECT domain FROM v_elections LIMIT 2")
+        .fetch_all(&mut *conn)
+        .await
+        .context("resolve domain")?;
+    match &rows[..] {
+        [] => Err(anyhow!("No Election Set").into()),
+        [(domain,)] => Ok(domain.clone()),
+        _ => Err(anyhow!("Election domain required").into()),
+    }
+}
+
+// The election of the vote chain with its nf root and initial cmx tree
+#[cfg(feature = "server")]
+pub async fn get_vote_election(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+) -> ZCVResult<(ElectionPropsPub, Vec<u8>, Vec<u8>)> {
+    let row: Option<(String, Vec<u8>, Vec<u8>)> =
+        query_as("SELECT data, nf_root, cmx_tree FROM v_elections WHERE domain = ?1")
+            .bind(domain)
+            .fetch_optional(&mut *conn)
+            .await
+            .context("get vote election")?;
+    let (data, nf_root, cmx_tree) = row.ok_or(anyhow!("Unknown election"))?;
+    let e = serde_json::from_str::<ElectionPropsPub>(&data)?;
+    Ok((e, nf_root, cmx_tree))
+}
+
+#[cfg(feature = "server")]
+pub async fn store_vote_height(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+    height: u32,
+) -> ZCVResult<()> {
+    query("UPDATE v_elections SET height = ?2 WHERE domain = ?1")
+        .bind(domain)
+        .bind(height)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Last vote height of the election with ballots
+#[cfg(feature = "server")]
+pub async fn get_vote_height(conn: &mut SqliteConnection, domain: &[u8]) -> ZCVResult<u32> {
+    let (height,): (u32,) = query_as("SELECT height FROM v_elections WHERE domain = ?1")
+        .bind(domain)
+        .fetch_one(conn)
+        .await
+        .context("get vote height")?;
+    Ok(height)
+}
+
+#[cfg(feature = "server")]
+pub async fn store_vote_frontier(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+    edge: &Edge,
+) -> ZCVResult<()> {
+    let mut bytes = vec![];
+    edge.write(&mut bytes).anyhow()?;
+    query("UPDATE v_elections SET frontier = ?2 WHERE domain = ?1")
+        .bind(domain)
+        .bind(bytes.as_slice())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+pub async fn store_block_state(
+    conn: &mut SqliteConnection,
+    block_height: u32,
+    block_hash: &[u8],
+    apphash: &[u8],
+) -> ZCVResult<()> {
+    query("UPDATE v_state SET block_height = ?1, block_hash = ?2, apphash = ?3 WHERE id = 0")
+        .bind(block_height)
+        .bind(block_hash)
+        .bind(apphash)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+pub async fn get_block_state(conn: &mut SqliteConnection) -> ZCVResult<(u32, Vec<u8>)> {
+    let (block_height, apphash): (u32, Vec<u8>) =
+        query_as("SELECT block_height, apphash FROM v_state WHERE id = 0")
+            .fetch_one(conn)
+            .await
+            .context("get block state")?;
+    Ok((block_height, apphash))
+}
+
+#[cfg(feature = "server")]
+pub async fn store_authority(conn: &mut SqliteConnection, authority: &[u8]) -> ZCVResult<()> {
+    query("UPDATE v_state SET authority = ?1 WHERE id = 0")
+        .bind(authority)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+pub async fn store_chain_id(conn: &mut SqliteConnection, chain_id: &str) -> ZCVResult<()> {
+    query("UPDATE v_state SET chain_id = ?1 WHERE id = 0")
+        .bind(chain_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+pub async fn store_admin_nonce(conn: &mut SqliteConnection, nonce: u64) -> ZCVResult<()> {
+    query("UPDATE v_state SET admin_nonce = ?1 WHERE id = 0")
+        .bind(nonce as i64)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// A power of 0 removes the validator
+#[cfg(feature = "server")]
+pub async fn store_validator(
+    conn: &mut SqliteConnection,
+    pub_key: &[u8],
+    power: u32,
+) -> ZCVResult<()> {
+    if power == 0 {
+        query("DELETE FROM v_validators WHERE pub_key = ?1")
+            .bind(pub_key)
+            .execute(conn)
+            .await?;
+    } else {
+        query(
+            "INSERT INTO v_validators(pub_key, power) VALUES (?1, ?2)
+            ON CONFLICT DO UPDATE SET power = excluded.power",
+        )
+        .bind(pub_key)
+        .bind(power)
+        .execute(conn)
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+pub async fn list_validators(conn: &mut SqliteConnection) -> ZCVResult<Vec<(Vec<u8>, u32)>> {
+    let validators: Vec<(Vec<u8>, u32)> =
+        query_as("SELECT pub_key, power FROM v_validators ORDER BY pub_key")
+            .fetch_all(conn)
+            .await
+            .context("list validators")?;
+    Ok(validators)
+}
+
+#[cfg(feature = "server")]
+pub async fn get_admin_state(conn: &mut SqliteConnection) -> ZCVResult<(String, Vec<u8>, u64)> {
+    let (chain_id, authority, nonce): (String, Vec<u8>, i64) =
+        query_as("SELECT chain_id, authority, admin_nonce FROM v_state WHERE id = 0")
+            .fetch_one(conn)
+            .await
+            .context("get admin state")?;
+    Ok((chain_id, authority, nonce as u64))
+}
+
 pub async fn store_election_frontier(conn: &mut SqliteConnection, edge: &Edge) -> ZCVResult<()> {
     let mut bytes = vec![];
     edge.write(&mut bytes).anyhow()?;
@@ -670,6 +1096,7 @@ pub async fn store_ballot(
     ballot: Ballot,
 ) -> ZCVResult<Option<u32>> {
     let Ballot { data, witnesses } = ballot;
+    let sighash = data.sighash()?;
     let mut data_bytes = vec![];
     data.write(&mut data_bytes).anyhow()?;
     let mut witnesses_bytes = vec![];
@@ -677,15 +1104,17 @@ pub async fn store_ballot(
 
     let mut db_tx = conn.begin().await?;
     let id_ballot = query(
-        "INSERT INTO v_ballots(height, itx, data, witnesses)
-    VALUES (?, ?, ?, ?)
+        "INSERT INTO v_ballots(domain, height, itx, data, witnesses, sighash)
+    VALUES (?, ?, ?, ?, ?, ?)
     ON CONFLICT DO NOTHING
     RETURNING id_ballot",
     )
+    .bind(data.domain.as_slice())
     .bind(height)
     .bind(itx)
     .bind(&data_bytes)
     .bind(&witnesses_bytes)
+    .bind(&sighash[..])
     .map(|r: SqliteRow| r.get::<u32, _>(0))
     .fetch_optional(&mut *db_tx)
     .await
@@ -693,9 +1122,10 @@ pub async fn store_ballot(
     if let Some(id_ballot) = id_ballot {
         for (idx, a) in data.actions.iter().enumerate() {
             query(
-                "INSERT INTO v_actions(ballot, idx, height, dnf, cmx)
-        VALUES (?1, ?2, ?3, ?4, ?5)",
+                "INSERT INTO v_actions(domain, ballot, idx, height, dnf, cmx)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
             )
+            .bind(data.domain.as_slice())
             .bind(id_ballot)
             .bind(idx as u32)
             .bind(height)
@@ -710,47 +1140,444 @@ pub async fn store_ballot(
 }
 
 #[cfg(feature = "server")]
-pub async fn get_ballot_range(
-    mut conn: SqliteConnection,
-    start: u32,
-    end: u32,
-    handler: impl Fn(crate::vote_rpc::Ballot) -> Pin<Box<dyn Future<Output = ZCVResult<()>> + Send>>
-    + 'static
-    + Send
-    + Sync,
+pub async fn store_snapshot(
+    conn: &mut SqliteConnection,
+    snapshot: &tendermint_proto::abci::Snapshot,
+    chunks: &[Vec<u8>],
 ) -> ZCVResult<()> {
-    tokio::spawn(async move {
-        let mut s = query(
-            "SELECT height, itx, data, witnesses FROM v_ballots
-    WHERE height >= ?1 AND height <= ?2 ORDER BY height, itx",
+    let mut db_tx = conn.begin().await?;
+    query(
+        "INSERT INTO v_snapshots(height, format, chunks, hash, metadata)
+        VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT DO NOTHING",
+    )
+    .bind(snapshot.height as i64)
+    .bind(snapshot.format)
+    .bind(snapshot.chunks)
+    .bind(snapshot.hash.as_ref())
+    .bind(snapshot.metadata.as_ref())
+    .execute(&mut *db_tx)
+    .await?;
+    for (idx, chunk) in chunks.iter().enumerate() {
+        query(
+            "INSERT INTO v_snapshot_chunks(height, idx, data)
+            VALUES (?1, ?2, ?3) ON CONFLICT DO NOTHING",
         )
-        .bind(start)
-        .bind(end)
-        .fetch(&mut conn);
-        while let Some(r) = s.next().await {
-            if let Ok(r) = r {
-                let height: u32 = r.get(0);
-                let itx: u32 = r.get(1);
-                let data: Vec<u8> = r.get(2);
-                let witnesses: Vec<u8> = r.get(3);
-                let data = BallotData::read(&*data)?;
-                let witnesses = BallotWitnesses::read(&*witnesses)?;
-                let b = Ballot { data, witnesses };
-                let mut ballot = vec![];
-                b.write(&mut ballot)?;
-                let ballot = crate::vote_rpc::Ballot {
-                    height,
-                    itx,
-                    ballot,
-                };
-                handler(ballot).await?;
-            }
-        }
-        Ok::<_, anyhow::Error>(())
-    });
+        .bind(snapshot.height as i64)
+        .bind(idx as u32)
+        .bind(chunk)
+        .execute(&mut *db_tx)
+        .await?;
+    }
+    db_tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+pub async fn list_snapshots(
+    conn: &mut SqliteConnection,
+) -> ZCVResult<Vec<tendermint_proto::abci::Snapshot>> {
+    let snapshots: Vec<(i64, u32, u32, Vec<u8>, Vec<u8>)> = query_as(
+        "SELECT height, format, chunks, hash, metadata FROM v_snapshots
+        ORDER BY height DESC",
+    )
+    .fetch_all(conn)
+    .await
+    .context("list snapshots")?;
+    Ok(snapshots
+        .into_iter()
+        .map(
+            |(height, format, chunks, hash, metadata)| tendermint_proto::abci::Snapshot {
+                height: height as u64,
+                format,
+                chunks,
+                hash: hash.into(),
+                metadata: metadata.into(),
+            },
+        )
+        .collect())
+}
+
+#[cfg(feature = "server")]
+pub async fn get_snapshot_chunk(
+    conn: &mut SqliteConnection,
+    height: u64,
+    format: u32,
+    idx: u32,
+) -> ZCVResult<Option<Vec<u8>>> {
+    let chunk: Option<(Vec<u8>,)> = query_as(
+        "SELECT c.data FROM v_snapshot_chunks c
+        JOIN v_snapshots s ON s.height = c.height
+        WHERE c.height = ?1 AND s.format = ?2 AND c.idx = ?3",
+    )
+    .bind(height as i64)
+    .bind(format)
+    .bind(idx)
+    .fetch_optional(conn)
+    .await
+    .context("get snapshot chunk")?;
+    Ok(chunk.map(|(data,)| data))
+}
+
+// Keep only the most recent snapshots
+#[cfg(feature = "server")]
+pub async fn prune_snapshots(conn: &mut SqliteConnection, keep: u32) -> ZCVResult<()> {
+    let mut db_tx = conn.begin().await?;
+    query(
+        "DELETE FROM v_snapshots WHERE height NOT IN
+        (SELECT height FROM v_snapshots ORDER BY height DESC LIMIT ?1)",
+    )
+    .bind(keep)
+    .execute(&mut *db_tx)
+    .await?;
+    query("DELETE FROM v_snapshot_chunks WHERE height NOT IN (SELECT height FROM v_snapshots)")
+        .execute(&mut *db_tx)
+        .await?;
+    db_tx.commit().await?;
     Ok(())
 }
 
+// Lookups by key, restricted to rows stored at or before the block
+// max_height. Rows are stored at the vote height of their election,
+// ie. the end of the election + the block height
+
+#[cfg(feature = "server")]
+pub async fn get_ballot_by_sighash(
+    conn: &mut SqliteConnection,
+    sighash: &[u8],
+    max_height: u32,
+) -> ZCVResult<Option<crate::vote_rpc::Ballot>> {
+    let r: Option<(u32, u32, Vec<u8>, Vec<u8>)> = query_as(
+        "SELECT b.height, b.itx, b.data, b.witnesses FROM v_ballots b
+        JOIN v_elections e ON e.domain = b.domain
+        WHERE b.sighash = ?1 AND b.height <= e.end + ?2",
+    )
+    .bind(sighash)
+    .bind(max_height)
+    .fetch_optional(&mut *conn)
+    .await
+    .context("get ballot by sighash")?;
+    let Some((height, itx, data, witnesses)) = r else {
+        return Ok(None);
+    };
+    let data = BallotData::read(&*data).anyhow()?;
+    let witnesses = BallotWitnesses::read(&*witnesses).anyhow()?;
+    let mut ballot = vec![];
+    Ballot { data, witnesses }.write(&mut ballot).anyhow()?;
+    Ok(Some(crate::vote_rpc::Ballot {
+        height,
+        itx,
+        ballot,
+        ..Default::default()
+    }))
+}
+
+// Returns the height and the sighash of the ballot that spent the nullifier
+#[cfg(feature = "server")]
+pub async fn get_nullifier(
+    conn: &mut SqliteConnection,
+    dnf: &[u8],
+    max_height: u32,
+) -> ZCVResult<Option<(u32, Vec<u8>)>> {
+    let r: Option<(u32, Option<Vec<u8>>)> = query_as(
+        "SELECT a.height, b.sighash FROM v_actions a
+        JOIN v_ballots b ON b.id_ballot = a.ballot
+        JOIN v_elections e ON e.domain = a.domain
+        WHERE a.dnf = ?1 AND a.height <= e.end + ?2",
+    )
+    .bind(dnf)
+    .bind(max_height)
+    .fetch_optional(&mut *conn)
+    .await
+    .context("get nullifier")?;
+    Ok(r.map(|(height, sighash)| (height, sighash.unwrap_or_default())))
+}
+
+// Returns the domain of the election and the vote height of the root
+#[cfg(feature = "server")]
+pub async fn get_cmx_root_height(
+    conn: &mut SqliteConnection,
+    cmx_root: &[u8],
+    max_height: u32,
+) -> ZCVResult<Option<(Vec<u8>, u32)>> {
+    let r: Option<(Vec<u8>, u32)> = query_as(
+        "SELECT c.domain, c.height FROM vs_cmxs c
+        JOIN v_elections e ON e.domain = c.domain
+        WHERE c.cmx = ?1 AND c.height <= e.end + ?2",
+    )
+    .bind(cmx_root)
+    .bind(max_height)
+    .fetch_optional(&mut *conn)
+    .await
+    .context("get cmx root height")?;
+    Ok(r)
+}
+
+// Height and CometBFT hash of the latest block
+#[cfg(feature = "server")]
+pub async fn get_block_hash(conn: &mut SqliteConnection) -> ZCVResult<(u32, Vec<u8>)> {
+    let r: (u32, Vec<u8>) =
+        query_as("SELECT block_height, block_hash FROM v_state WHERE id = 0")
+            .fetch_one(conn)
+            .await
+            .context("get block hash")?;
+    Ok(r)
+}
+
+// cmx roots of the election that were current at a vote height
+// of [start, end]: the root at start followed by the ones added up to end
+#[cfg(feature = "server")]
+pub async fn get_cmx_roots(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+    start: u32,
+    end: u32,
+) -> ZCVResult<Vec<(u32, Vec<u8>)>> {
+    let roots: Vec<(u32, Vec<u8>)> = query_as(
+        "SELECT height, cmx FROM vs_cmxs WHERE domain = ?3 AND height = (SELECT MAX(height)
+        FROM vs_cmxs WHERE domain = ?3 AND height <= ?1)
+        UNION ALL
+        SELECT height, cmx FROM vs_cmxs WHERE domain = ?3 AND height > ?1 AND height <= ?2
+        ORDER BY height",
+    )
+    .bind(start)
+    .bind(end)
+    .bind(domain)
+    .fetch_all(&mut *conn)
+    .await
+    .context("get cmx roots")?;
+    Ok(roots)
+}
+
+// Every spent domain nullifier with the sighash of its ballot,
+// in the order they were stored
+#[cfg(feature = "server")]
+pub async fn list_nullifiers(conn: &mut SqliteConnection) -> ZCVResult<Vec<(Vec<u8>, Vec<u8>)>> {
+    let nfs: Vec<(Vec<u8>, Vec<u8>)> = query_as(
+        "SELECT a.dnf, b.sighash FROM v_actions a JOIN v_ballots b ON b.id_ballot = a.ballot
+        ORDER BY a.id_action",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .context("list nullifiers")?;
+    Ok(nfs)
+}
+
+// Output cmxs of the ballots of the election with their vote height,
+// in the order they were appended to the cmx tree
+#[cfg(feature = "server")]
+pub async fn list_action_cmxs(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+) -> ZCVResult<Vec<(u32, Vec<u8>)>> {
+    let cmxs: Vec<(u32, Vec<u8>)> = query_as(
+        "SELECT a.height, a.cmx FROM v_actions a JOIN v_ballots b ON b.id_ballot = a.ballot
+        WHERE a.domain = ?1 ORDER BY a.height, b.itx, a.idx",
+    )
+    .bind(domain)
+    .fetch_all(&mut *conn)
+    .await
+    .context("list action cmxs")?;
+    Ok(cmxs)
+}
+
+#[cfg(feature = "server")]
+pub async fn clear_cmx_roots(conn: &mut SqliteConnection, domain: &[u8]) -> ZCVResult<()> {
+    query("DELETE FROM vs_cmxs WHERE domain = ?1")
+        .bind(domain)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Ballots of the election stored in [start, end], in chain order
+#[cfg(feature = "server")]
+pub async fn list_ballots(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+    start: u32,
+    end: u32,
+) -> ZCVResult<Vec<crate::vote_rpc::Ballot>> {
+    let rows: Vec<(u32, u32, Vec<u8>, Vec<u8>)> = query_as(
+        "SELECT height, itx, data, witnesses FROM v_ballots
+        WHERE domain = ?3 AND height >= ?1 AND height <= ?2 ORDER BY height, itx",
+    )
+    .bind(start)
+    .bind(end)
+    .bind(domain)
+    .fetch_all(&mut *conn)
+    .await
+    .context("list ballots")?;
+    let mut ballots = vec![];
+    for (height, itx, data, witnesses) in rows {
+        let data = BallotData::read(&*data).anyhow()?;
+        let witnesses = BallotWitnesses::read(&*witnesses).anyhow()?;
+        let mut ballot = vec![];
+        Ballot { data, witnesses }.write(&mut ballot).anyhow()?;
+        ballots.push(crate::vote_rpc::Ballot {
+            height,
+            itx,
+            ballot,
+            ..Default::default()
+        });
+    }
+    Ok(ballots)
+}
+
+// Counts of the committed ballots of the election, with ballots
+// grouped by buckets of bucket_size vote heights
+#[cfg(feature = "server")]
+pub async fn get_election_stats(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+    bucket_size: u32,
+) -> ZCVResult<crate::vote_rpc::ElectionStats> {
+    let (ballots, first_height, last_height): (u32, u32, u32) = query_as(
+        "SELECT COUNT(*), COALESCE(MIN(height), 0), COALESCE(MAX(height), 0)
+        FROM v_ballots WHERE domain = ?1",
+    )
+    .bind(domain)
+    .fetch_one(&mut *conn)
+    .await
+    .context("ballot stats")?;
+    let (actions,): (u32,) = query_as("SELECT COUNT(*) FROM v_actions WHERE domain = ?1")
+        .bind(domain)
+        .fetch_one(&mut *conn)
+        .await
+        .context("action stats")?;
+    // The frontier starts from the cmx tree of Zcash at the snapshot height
+    let frontier: Option<(Vec<u8>,)> =
+        query_as("SELECT frontier FROM v_elections WHERE domain = ?1")
+            .bind(domain)
+            .fetch_optional(&mut *conn)
+            .await
+            .context("cmx tree stats")?;
+    let cmx_tree_size = match frontier {
+        Some((frontier,)) if !frontier.is_empty() => {
+            Edge::read(frontier.as_slice()).anyhow()?.size() as u32
+        }
+        _ => 0,
+    };
+    let (cmx_roots,): (u32,) = query_as("SELECT COUNT(*) FROM vs_cmxs WHERE domain = ?1")
+        .bind(domain)
+        .fetch_one(&mut *conn)
+        .await
+        .context("cmx root stats")?;
+    let buckets: Vec<(u32, u32)> = query_as(
+        "SELECT height / ?1 * ?1 AS bucket, COUNT(*) FROM v_ballots
+        WHERE domain = ?2 GROUP BY bucket ORDER BY bucket",
+    )
+    .bind(bucket_size.max(1))
+    .bind(domain)
+    .fetch_all(&mut *conn)
+    .await
+    .context("ballot buckets")?;
+    let buckets = buckets
+        .into_iter()
+        .map(|(start, ballots)| crate::vote_rpc::HeightBucket { start, ballots })
+        .collect();
+    Ok(crate::vote_rpc::ElectionStats {
+        ballots,
+        actions,
+        buckets,
+        first_height,
+        last_height,
+        cmx_tree_size,
+        cmx_roots,
+    })
+}
+
+// At most limit ballots of the election in [start, end] that come
+// after the cursor (height, itx), in chain order
+#[cfg(feature = "server")]
+pub async fn list_ballot_page(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+    start: u32,
+    end: u32,
+    after: Option<(u32, u32)>,
+    limit: u32,
+) -> ZCVResult<Vec<crate::vote_rpc::Ballot>> {
+    let (after_height, after_itx) = after.map_or((0, -1), |(h, i)| (h, i as i64));
+    let rows: Vec<(u32, u32, Vec<u8>, Vec<u8>)> = query_as(
+        "SELECT height, itx, data, witnesses FROM v_ballots
+        WHERE domain = ?6 AND height >= ?1 AND height <= ?2
+        AND (height > ?3 OR (height = ?3 AND itx > ?4))
+        ORDER BY height, itx LIMIT ?5",
+    )
+    .bind(start)
+    .bind(end)
+    .bind(after_height)
+    .bind(after_itx)
+    .bind(limit)
+    .bind(domain)
+    .fetch_all(&mut *conn)
+    .await
+    .context("list ballot page")?;
+    let mut ballots = vec![];
+    for (height, itx, data, witnesses) in rows {
+        let data = BallotData::read(&*data).anyhow()?;
+        let witnesses = BallotWitnesses::read(&*witnesses).anyhow()?;
+        let mut ballot = vec![];
+        Ballot { data, witnesses }.write(&mut ballot).anyhow()?;
+        ballots.push(crate::vote_rpc::Ballot {
+            height,
+            itx,
+            ballot,
+            ..Default::default()
+        });
+    }
+    Ok(ballots)
+}
+
+// Pass the ballots of the election in [start, end] that come after
+// the cursor (height, itx) to the handler, in chain order.
+// Stops at the first error. Returns the cursor of the last ballot
+#[cfg(feature = "server")]
+pub async fn get_ballot_range<F: Future<Output = ZCVResult<()>>>(
+    conn: &mut SqliteConnection,
+    domain: &[u8],
+    start: u32,
+    end: u32,
+    after: Option<(u32, u32)>,
+    mut handler: impl FnMut(crate::vote_rpc::Ballot) -> F,
+) -> ZCVResult<Option<(u32, u32)>> {
+    let (after_height, after_itx) = after.map_or((0, -1), |(h, i)| (h, i as i64));
+    let mut s = query(
+        "SELECT height, itx, data, witnesses FROM v_ballots
+    WHERE domain = ?5 AND height >= ?1 AND height <= ?2
+    AND (height > ?3 OR (height = ?3 AND itx > ?4))
+    ORDER BY height, itx",
+    )
+    .bind(start)
+    .bind(end)
+    .bind(after_height)
+    .bind(after_itx)
+    .bind(domain)
+    .fetch(&mut *conn);
+    let mut last = after;
+    while let Some(r) = s.next().await {
+        let r = r?;
+        let height: u32 = r.get(0);
+        let itx: u32 = r.get(1);
+        let data: Vec<u8> = r.get(2);
+        let witnesses: Vec<u8> = r.get(3);
+        let data = BallotData::read(&*data).anyhow()?;
+        let witnesses = BallotWitnesses::read(&*witnesses).anyhow()?;
+        let b = Ballot { data, witnesses };
+        let mut ballot = vec![];
+        b.write(&mut ballot).anyhow()?;
+        let ballot = crate::vote_rpc::Ballot {
+            height,
+            itx,
+            ballot,
+            ..Default::default()
+        };
+        handler(ballot).await?;
+        last = Some((height, itx));
+    }
+    Ok(last)
+}
+
 #[cfg(any(feature = "tally", feature = "client", feature = "server"))]
 pub async fn store_result(conn: &mut SqliteConnection, memo: &[u8], value: u64) -> ZCVResult<()> {
     query(
@@ -767,13 +1594,17 @@ pub async fn store_result(conn: &mut SqliteConnection, memo: &[u8], value: u64)
 #[cfg(test)]
 mod tests {
     use crate::{
-        db::{get_domain, get_election, set_account_seed, store_ballot},
+        db::{
+            column_exists, create_schema, get_block_hash, get_block_state, get_cmx_roots, get_domain, get_election,
+            get_election_stats, list_ballot_page, set_account_seed, store_ballot, store_block_state, store_cmx_root,
+        },
         tests::{get_connection, test_setup},
     };
     use anyhow::Result;
     use ff::PrimeField;
     use orchard_vote::{Ballot, BallotAnchors, BallotData, BallotWitnesses};
-    use sqlx::{query, query_as};
+    use sqlx::{Connection, SqliteConnection, query, query_as};
+    use zcash_trees::warp::{Edge, hasher::OrchardHasher};
 
     #[tokio::test]
     async fn test_schema_creation() -> Result<()> {
@@ -790,6 +1621,96 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_schema_upgrade() -> Result<()> {
+        let mut conn = get_connection().await?;
+        // The server columns are added once, creating the schema again is a no-op
+        create_schema(&mut conn).await?;
+        assert_eq!(column_exists(&mut conn, "v_state", "block_hash").await?, Some(true));
+        Ok(())
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_schema_migration() -> Result<()> {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await?;
+        // A node of version 4 with a wallet note and a ballot of its election
+        for sql in [
+            "CREATE TABLE v_state(id INTEGER PRIMARY KEY, version INTEGER, account INTEGER,
+            url TEXT, height INTEGER NOT NULL DEFAULT 0, frontier BLOB NOT NULL DEFAULT (X''),
+            locked BOOL NOT NULL DEFAULT 0)",
+            "INSERT INTO v_state(id, version, height, frontier, locked) VALUES (0, 4, 120, X'0102', 1)",
+            "CREATE TABLE v_elections(id_election INTEGER PRIMARY KEY, name TEXT NOT NULL,
+            end INTEGER NOT NULL, need_sig BOOL NOT NULL, domain BLOB NOT NULL,
+            address TEXT NOT NULL, data TEXT NOT NULL, nf_root BLOB NOT NULL DEFAULT (X''),
+            cmx_tree BLOB NOT NULL DEFAULT (X''), UNIQUE (domain))",
+            "INSERT INTO v_elections(id_election, name, end, need_sig, domain, address, data)
+            VALUES (0, '', 100, FALSE, X'01', '', '')",
+            "CREATE TABLE v_notes(id_note INTEGER PRIMARY KEY, account INTEGER NOT NULL,
+            height INTEGER NOT NULL, scope INTEGER NOT NULL, position INTEGER NOT NULL,
+            nf BLOB NOT NULL, dnf BLOB NOT NULL, rho BLOB NOT NULL, diversifier BLOB NOT NULL,
+            rseed BLOB NOT NULL, value INTEGER NOT NULL, memo BLOB NOT NULL, UNIQUE (position))",
+            "INSERT INTO v_notes(account, height, scope, position, nf, dnf, rho, diversifier,
+            rseed, value, memo) VALUES (0, 1, 0, 0, X'', X'', X'', X'', X'', 10, X'')",
+            "CREATE TABLE v_ballots(id_ballot INTEGER PRIMARY KEY, height INTEGER NOT NULL,
+            itx INTEGER NOT NULL, data BLOB NOT NULL, witnesses BLOB NOT NULL, sighash BLOB,
+            UNIQUE (height, itx))",
+            "CREATE INDEX i_ballots_sighash ON v_ballots(sighash)",
+            "INSERT INTO v_ballots(id_ballot, height, itx, data, witnesses, sighash)
+            VALUES (1, 110, 0, X'', X'', X'AA')",
+            "CREATE TABLE v_actions(id_action INTEGER PRIMARY KEY, height INTEGER NOT NULL,
+            ballot INTEGER NOT NULL, idx INTEGER NOT NULL, dnf BLOB NOT NULL, cmx BLOB NOT NULL,
+            UNIQUE (dnf))",
+            "INSERT INTO v_actions(height, ballot, idx, dnf, cmx) VALUES (110, 1, 0, X'02', X'03')",
+            "CREATE TABLE vs_cmxs(cmx BLOB PRIMARY KEY NOT NULL, height INTEGER)",
+            "INSERT INTO vs_cmxs(cmx, height) VALUES (X'04', 110)",
+        ] {
+            query(sql).execute(&mut conn).await?;
+        }
+        create_schema(&mut conn).await?;
+
+        let (version,): (u32,) = query_as("SELECT version FROM v_state WHERE id = 0")
+            .fetch_one(&mut conn)
+            .await?;
+        assert_eq!(version, 5);
+        let (notes,): (u32,) = query_as("SELECT COUNT(*) FROM v_notes")
+            .fetch_one(&mut conn)
+            .await?;
+        assert_eq!(notes, 1);
+        let election: (u32, Vec<u8>, bool) =
+            query_as("SELECT height, frontier, locked FROM v_elections WHERE id_election = 0")
+                .fetch_one(&mut conn)
+                .await?;
+        assert_eq!(election, (120, vec![1, 2], true));
+        for table in ["v_ballots", "v_actions", "vs_cmxs"] {
+            let (domain,): (Vec<u8>,) = query_as(&format!("SELECT domain FROM {table}"))
+                .fetch_one(&mut conn)
+                .await?;
+            assert_eq!(domain, [1]);
+        }
+        let (tables,): (u32,) = query_as(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name LIKE 'v4_%'",
+        )
+        .fetch_one(&mut conn)
+        .await?;
+        assert_eq!(tables, 0);
+        // Keyed by domain, another election can have the same cmx root
+        store_cmx_root(&mut conn, &[2], &[4], 110).await?;
+        let (cmxs,): (u32,) = query_as("SELECT COUNT(*) FROM vs_cmxs")
+            .fetch_one(&mut conn)
+            .await?;
+        assert_eq!(cmxs, 2);
+        let (index,): (u32,) = query_as(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index'
+            AND name = 'i_ballots_sighash' AND tbl_name = 'v_ballots'",
+        )
+        .fetch_one(&mut conn)
+        .await?;
+        assert_eq!(index, 1);
+        Ok(())
+    }
+
     #[cfg(feature = "client")]
     #[tokio::test]
     async fn test_invalid_seed() -> Result<()> {
@@ -839,4 +1760,146 @@ mod tests {
         assert_eq!(count_ballot, 1);
         Ok(())
     }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_list_ballot_page() -> Result<()> {
+        let mut conn = get_connection().await?;
+        test_setup(&mut conn).await?;
+        query("DELETE FROM v_ballots").execute(&mut *conn).await?;
+        let (election, ..) = get_election(&mut conn).await?;
+        let (domain, _address) = get_domain(&mut conn).await?;
+        let dummy_ballot = || Ballot {
+            data: BallotData {
+                version: 1,
+                domain: domain.to_repr(),
+                actions: vec![],
+                anchors: BallotAnchors {
+                    nf: [0; 32],
+                    cmx: [0; 32],
+                },
+            },
+            witnesses: BallotWitnesses {
+                proofs: vec![],
+                sp_signatures: None,
+                binding_signature: [0u8; 64],
+            },
+        };
+        let h = election.end + 1;
+        for (height, itx) in [(h, 0), (h, 1), (h + 1, 0)] {
+            store_ballot(&mut conn, height, itx, dummy_ballot()).await?;
+        }
+        let cursor = |b: &crate::vote_rpc::Ballot| (b.height, b.itx);
+        let domain = domain.to_repr();
+        let page = list_ballot_page(&mut conn, &domain, h, h + 1, None, 2).await?;
+        assert_eq!(page.iter().map(cursor).collect::<Vec<_>>(), [(h, 0), (h, 1)]);
+        let page = list_ballot_page(&mut conn, &domain, h, h + 1, Some((h, 1)), 2).await?;
+        assert_eq!(page.iter().map(cursor).collect::<Vec<_>>(), [(h + 1, 0)]);
+        let page = list_ballot_page(&mut conn, &domain, h, h, Some((h, 1)), 2).await?;
+        assert!(page.is_empty());
+        // Other elections have no ballots
+        let page = list_ballot_page(&mut conn, &[0u8; 32], h, h + 1, None, 2).await?;
+        assert!(page.is_empty());
+        Ok(())
+    }
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_block_state() -> Result<()> {
+        let mut conn = get_connection().await?;
+        store_block_state(&mut conn, 42, &[1u8; 32], &[7u8; 32]).await?;
+        let (block_height, apphash) = get_block_state(&mut conn).await?;
+        assert_eq!(block_height, 42);
+        assert_eq!(apphash, vec![7u8; 32]);
+        assert_eq!(get_block_hash(&mut conn).await?, (42, vec![1u8; 32]));
+        Ok(())
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_cmx_roots() -> Result<()> {
+        let mut conn = get_connection().await?;
+        query("DELETE FROM vs_cmxs").execute(&mut *conn).await?;
+        let (d1, d2) = ([1u8; 32], [2u8; 32]);
+        store_cmx_root(&mut conn, &d1, &[1u8; 32], 10).await?;
+        store_cmx_root(&mut conn, &d1, &[2u8; 32], 12).await?;
+        store_cmx_root(&mut conn, &d1, &[3u8; 32], 15).await?;
+        // Same initial root in another election
+        store_cmx_root(&mut conn, &d2, &[1u8; 32], 11).await?;
+        // The root at 11 is the one of height 10
+        let roots = get_cmx_roots(&mut conn, &d1, 11, 14).await?;
+        assert_eq!(roots, vec![(10, vec![1u8; 32]), (12, vec![2u8; 32])]);
+        let roots = get_cmx_roots(&mut conn, &d1, 20, 20).await?;
+        assert_eq!(roots, vec![(15, vec![3u8; 32])]);
+        assert!(get_cmx_roots(&mut conn, &d1, 5, 9).await?.is_empty());
+        let roots = get_cmx_roots(&mut conn, &d2, 11, 14).await?;
+        assert_eq!(roots, vec![(11, vec![1u8; 32])]);
+        Ok(())
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_election_stats() -> Result<()> {
+        let mut conn = get_connection().await?;
+        for table in ["v_ballots", "v_actions", "vs_cmxs"] {
+            query(&format!("DELETE FROM {table}"))
+                .execute(&mut *conn)
+                .await?;
+        }
+        let domain = [1u8; 32];
+        let stats = get_election_stats(&mut conn, &domain, 10).await?;
+        assert_eq!(stats.ballots, 0);
+        assert_eq!(stats.first_height, 0);
+        assert!(stats.buckets.is_empty());
+
+        for (id, height) in [(1u32, 101u32), (2, 105), (3, 123)] {
+            query(
+                "INSERT INTO v_ballots(id_ballot, domain, height, itx, data, witnesses)
+                VALUES (?1, ?3, ?2, 0, X'', X'')",
+            )
+            .bind(id)
+            .bind(height)
+            .bind(domain.as_slice())
+            .execute(&mut *conn)
+            .await?;
+            query(
+                "INSERT INTO v_actions(domain, ballot, idx, height, dnf, cmx)
+                VALUES (?4, ?1, 0, ?2, ?3, ?3)",
+            )
+            .bind(id)
+            .bind(height)
+            .bind(vec![id as u8; 32])
+            .bind(domain.as_slice())
+            .execute(&mut *conn)
+            .await?;
+            store_cmx_root(&mut conn, &domain, &[id as u8; 32], height).await?;
+        }
+        // 2 notes in the cmx tree of the snapshot and the 3 of the ballots
+        let hasher = OrchardHasher::default();
+        let mut edge = Edge::default();
+        for i in 1..=5u8 {
+            edge.append(&hasher, [i; 32]);
+        }
+        let mut frontier = vec![];
+        edge.write(&mut frontier)?;
+        query(
+            "INSERT INTO v_elections(name, end, need_sig, domain, address, data, frontier)
+            VALUES ('', 100, FALSE, ?1, '', '', ?2)
+            ON CONFLICT (domain) DO UPDATE SET frontier = excluded.frontier",
+        )
+        .bind(domain.as_slice())
+        .bind(frontier)
+        .execute(&mut *conn)
+        .await?;
+        assert_eq!(get_election_stats(&mut conn, &[2u8; 32], 10).await?.ballots, 0);
+        let stats = get_election_stats(&mut conn, &domain, 10).await?;
+        assert_eq!(stats.ballots, 3);
+        assert_eq!(stats.actions, 3);
+        assert_eq!(stats.first_height, 101);
+        assert_eq!(stats.last_height, 123);
+        assert_eq!(stats.cmx_tree_size, 5);
+        assert_eq!(stats.cmx_roots, 3);
+        let buckets: Vec<_> = stats.buckets.iter().map(|b| (b.start, b.ballots)).collect();
+        assert_eq!(buckets, [(100, 2), (120, 1)]);
+        Ok(())
+    }
 }
@@ -1,6 +1,8 @@
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
 
 pub mod error;
+pub mod authority;
+pub mod pow;
 pub mod pod;
 pub mod context;
 pub mod db;
@@ -25,6 +27,10 @@ pub mod rpc;
 #[path = "cash.z.vote.sdk.rpc.rs"]
 pub mod vote_rpc;
 
+// Encoded descriptors of vote.proto for the gRPC reflection service
+#[cfg(feature = "server")]
+pub const VOTE_FILE_DESCRIPTOR_SET: &[u8] = include_bytes!("vote_descriptor.bin");
+
 // pub mod frb_generated;
 
 pub use error::ZCVResult;
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use crate::{
     ZCVResult,
@@ -12,7 +12,10 @@ use crate::{
     pod::ImtProofDataBin,
     rpc::{BlockId, compact_tx_streamer_client::CompactTxStreamerClient},
     tiu,
-    vote_rpc::{VoteRange, vote_streamer_client::VoteStreamerClient},
+    vote_rpc::{
+        Ballot, VoteCursor, VoteRange, VoteRangeItem, VoteSubscription,
+        vote_range_item::Item, vote_streamer_client::VoteStreamerClient,
+    },
 };
 use bincode::config::legacy;
 use ff::PrimeField;
@@ -24,8 +27,9 @@ use orchard_vote::try_decrypt_ballot;
 use pasta_curves::Fp;
 use pir_client::PirClient;
 use sqlx::{Acquire, SqliteConnection, query, query_as};
+use tokio_stream::{Stream, StreamExt};
 use tonic::{
-    Request,
+    Code, Request, Status, Streaming,
     transport::{Channel, Endpoint},
 };
 use tracing::info;
@@ -35,6 +39,14 @@ use zcash_trees::warp::{Edge, Hasher, Witness, hasher::OrchardHasher, legacy::Co
 pub type Client = CompactTxStreamerClient<Channel>;
 pub type VoteClient = VoteStreamerClient<Channel>;
 
+// follow_ballots scans the ballots it received after this idle time
+const FOLLOW_IDLE: Duration = Duration::from_secs(1);
+// or once it has this many ballots
+const FOLLOW_BATCH: usize = 1000;
+// An interrupted vote range is resumed up to RANGE_RETRIES times
+const RANGE_RETRIES: u32 = 5;
+const RANGE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 pub async fn connect(url: &str) -> ZCVResult<Client> {
     let ep = Endpoint::from_shared(url.to_string())?;
     let client = CompactTxStreamerClient::connect(ep).await?;
@@ -67,7 +79,7 @@ pub async fn fetch_initial_roots(lwd_url: &str, pir_url: &str, end: u32) -> ZCVR
 
 pub async fn fetch_roots(conn: &mut SqliteConnection) -> ZCVResult<(Vec<u8>, Vec<u8>)> {
     let (nf_root,): (Vec<u8>,) =
-        query_as("SELECT nf_root FROM v_elections WHERE id_election = 0")
+        query_as("SELECT nf_root FROM v_elections LIMIT 1")
             .fetch_one(&mut *conn)
             .await?;
     let (cmx_tree,): (Vec<u8>,) =
@@ -84,6 +96,7 @@ pub async fn scan_ballots(
     client: &mut VoteClient,
     pir_client: &PirClient,
     id_account: u32,
+    domain: &[u8],
     start: u32,
     end: u32,
 ) -> ZCVResult<()> {
@@ -92,6 +105,162 @@ pub async fn scan_ballots(
         tracing::info!("Skipping scan_ballots");
         return Ok(());
     }
+    let ballots = vote_range(client, domain, start + 1, end);
+    scan_ballot_stream(network, conn, pir_client, id_account, start, end, ballots).await
+}
+
+struct VoteRangeState {
+    client: VoteClient,
+    range: VoteRange,
+    items: Option<Streaming<VoteRangeItem>>,
+    retries: u32,
+    done: bool,
+}
+
+impl VoteRangeState {
+    // Returns the error if we should give up. Only the errors
+    // of the connection are retried
+    fn retry(&mut self, error: Status) -> Option<Status> {
+        self.items = None;
+        self.retries += 1;
+        let transient = matches!(error.code(), Code::Unavailable | Code::DeadlineExceeded);
+        if !transient || self.retries > RANGE_RETRIES {
+            self.done = true;
+            return Some(error);
+        }
+        tracing::warn!("Vote range interrupted: {error}, resuming after {:?}", self.range.after);
+        None
+    }
+}
+
+// Ballots of the election in [start, end]. The download resumes after
+// the last ballot received if the stream fails or is truncated
+pub fn vote_range(
+    client: &VoteClient,
+    domain: &[u8],
+    start: u32,
+    end: u32,
+) -> impl Stream<Item = Result<Ballot, Status>> + Unpin {
+    let state = VoteRangeState {
+        client: client.clone(),
+        range: VoteRange {
+            start,
+            end,
+            after: None,
+            domain: domain.to_vec(),
+        },
+        items: None,
+        retries: 0,
+        done: false,
+    };
+    Box::pin(futures::stream::unfold(state, |mut st| async move {
+        while !st.done {
+            if st.items.is_none() {
+                match st.client.get_vote_range(Request::new(st.range.clone())).await {
+                    Ok(rep) => st.items = Some(rep.into_inner()),
+                    Err(e) => {
+                        if let Some(e) = st.retry(e) {
+                            return Some((Err(e), st));
+                        }
+                        tokio::time::sleep(RANGE_RETRY_DELAY * st.retries).await;
+                        continue;
+                    }
+                }
+            }
+            let next = st.items.as_mut().unwrap().message().await;
+            let error = match next {
+                Ok(Some(VoteRangeItem {
+                    item: Some(Item::Ballot(ballot)),
+                })) => {
+                    st.range.after = Some(VoteCursor {
+                        height: ballot.height,
+                        itx: ballot.itx,
+                    });
+                    st.retries = 0;
+                    return Some((Ok(ballot), st));
+                }
+                Ok(Some(VoteRangeItem {
+                    item: Some(Item::End(_)),
+                })) => {
+                    st.done = true;
+                    continue;
+                }
+                Ok(Some(_)) => continue,
+                // Ended without the trailer
+                Ok(None) => Status::unavailable("Vote range truncated"),
+                Err(e) => e,
+            };
+            if let Some(e) = st.retry(error) {
+                return Some((Err(e), st));
+            }
+            tokio::time::sleep(RANGE_RETRY_DELAY * st.retries).await;
+        }
+        None
+    }))
+}
+
+// Keep scanning the ballots as the vote chain finalizes them
+// Returns when the server ends the subscription
+pub async fn follow_ballots(
+    network: &Network,
+    conn: &mut SqliteConnection,
+    client: &mut VoteClient,
+    pir_client: &PirClient,
+    id_account: u32,
+    domain: &[u8],
+    start: u32,
+) -> ZCVResult<()> {
+    tracing::info!("follow_ballots from {start}");
+    let mut ballots = client
+        .subscribe_votes(Request::new(VoteSubscription {
+            start: start + 1,
+            domain: domain.to_vec(),
+        }))
+        .await?
+        .into_inner();
+    let mut start = start;
+    let mut batch: Vec<Ballot> = vec![];
+    loop {
+        // The ballots of a block arrive together. Scan them once
+        // the subscription is idle or the batch is full
+        let (ballot, done) = match tokio::time::timeout(FOLLOW_IDLE, ballots.message()).await {
+            Ok(next) => {
+                let next = next?;
+                let done = next.is_none();
+                (next, done)
+            }
+            Err(_) => (None, false),
+        };
+        // Do not split the ballots of a height
+        if let Some(b) = &ballot
+            && (batch.len() < FOLLOW_BATCH || batch.last().is_none_or(|l| l.height == b.height))
+        {
+            batch.extend(ballot);
+            continue;
+        }
+        if let Some(end) = batch.last().map(|b| b.height) {
+            let ballots = tokio_stream::iter(std::mem::take(&mut batch).into_iter().map(Ok));
+            scan_ballot_stream(network, conn, pir_client, id_account, start, end, ballots).await?;
+            start = end + 1;
+        }
+        batch.extend(ballot);
+        if done {
+            return Ok(());
+        }
+    }
+}
+
+// Decrypt the ballots of [start, end] and update the witnesses
+// of our notes
+async fn scan_ballot_stream(
+    network: &Network,
+    conn: &mut SqliteConnection,
+    pir_client: &PirClient,
+    id_account: u32,
+    start: u32,
+    end: u32,
+    mut ballots: impl Stream<Item = Result<Ballot, Status>> + Unpin,
+) -> ZCVResult<()> {
     let mut db_tx = conn.begin().await?;
     crate::db::delete_range(&mut db_tx, start, end).await?;
     let mut ivks = vec![];
@@ -115,14 +284,6 @@ pub async fn scan_ballots(
         nfs.insert(tiu!(dnf), id_account);
     }
 
-    let mut ballots = client
-        .get_vote_range(Request::new(VoteRange {
-            start: (start + 1),
-            end,
-        }))
-        .await?
-        .into_inner();
-
     let hasher = OrchardHasher::default();
     tracing::info!("get_election_frontier");
     let cmx_tree_bytes = get_election_frontier(&mut db_tx).await?;
@@ -133,7 +294,8 @@ pub async fn scan_ballots(
     tracing::info!("ballot loop");
     let mut new_notes = vec![];
     let mut cmxs = vec![];
-    while let Some(ballot) = ballots.message().await? {
+    while let Some(ballot) = ballots.next().await {
+        let ballot = ballot?;
         let height = ballot.height;
         let ballot = orchard_vote::Ballot::read(&*ballot.ballot).anyhow()?;
         let data = &ballot.data;
@@ -282,6 +444,7 @@ pub async fn decode_ballots(
     conn: &mut SqliteConnection,
     client: &mut VoteClient,
     election_seed: &str,
+    domain: &[u8],
     start: u32,
     end: u32,
 ) -> ZCVResult<()> {
@@ -293,15 +456,9 @@ pub async fn decode_ballots(
 
     query("DELETE FROM v_results").execute(&mut *db_tx).await?;
 
-    let mut ballots = client
-        .get_vote_range(Request::new(VoteRange {
-            start: (start + 1),
-            end,
-        }))
-        .await?
-        .into_inner();
-
-    while let Some(ballot) = ballots.message().await? {
+    let mut ballots = vote_range(client, domain, start + 1, end);
+    while let Some(ballot) = ballots.next().await {
+        let ballot = ballot?;
         let height = ballot.height;
         let ballot = orchard_vote::Ballot::read(&*ballot.ballot).anyhow()?;
         let data = &ballot.data;
@@ -322,3 +479,39 @@ pub async fn decode_ballots(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use tonic::{Status, transport::Endpoint};
+
+    use crate::{
+        lwd::{RANGE_RETRIES, VoteRangeState},
+        vote_rpc::{VoteRange, vote_streamer_client::VoteStreamerClient},
+    };
+
+    #[tokio::test]
+    async fn test_vote_range_retry() {
+        let channel = Endpoint::from_static("http://127.0.0.1:1").connect_lazy();
+        let mut st = VoteRangeState {
+            client: VoteStreamerClient::new(channel),
+            range: VoteRange::default(),
+            items: None,
+            retries: 0,
+            done: false,
+        };
+        // The connection errors are retried a few times
+        assert!(st.retry(Status::unavailable("")).is_none());
+        assert!(st.retry(Status::deadline_exceeded("")).is_none());
+        while st.retries < RANGE_RETRIES {
+            assert!(st.retry(Status::unavailable("")).is_none());
+        }
+        assert!(st.retry(Status::unavailable("")).is_some());
+        assert!(st.done);
+
+        // The other errors are returned right away
+        let mut st = VoteRangeState { retries: 0, done: false, ..st };
+        let e = st.retry(Status::invalid_argument("Unknown election"));
+        assert_eq!(e.map(|e| e.code()), Some(tonic::Code::InvalidArgument));
+        assert!(st.done);
+    }
+}
@@ -1,5 +1,6 @@
+use anyhow::anyhow;
 use bech32::{Bech32m, Hrp};
-use bincode::{Decode, Encode};
+use bincode::{Decode, Encode, enc::Encoder, error::EncodeError};
 use ff::PrimeField;
 use orchard::{
     Note,
@@ -12,8 +13,11 @@ use pasta_curves::Fp;
 use pir_client::ImtProofData;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use zcash_protocol::consensus::Network;
 
-use crate::{ZCVResult, db::derive_spending_key, error::IntoAnyhow, tiu};
+use crate::{
+    ZCVResult, authority::authority_key, db::derive_spending_key, error::IntoAnyhow, tiu,
+};
 
 pub const ZCV_MNEMONIC_DOMAIN: &[u8] = b"ZCVote__Personal";
 
@@ -24,6 +28,20 @@ pub struct ElectionProps {
     pub secret_seed: Option<String>,
     pub pir: String,
     pub end: u32,
+    // Voting deadline. Ballots are rejected after the vote height
+    // close_height (end + vote chain height, like the heights of
+    // the ballots) or from close_time (unix time). 0 means no deadline
+    #[serde(default)]
+    pub close_height: u32,
+    #[serde(default)]
+    pub close_time: u64,
+    // Leading zero bits of the proof of work stamp of the ballots
+    // 0 means no stamp
+    #[serde(default)]
+    pub pow_bits: u32,
+    // Zcash network of the keys of the election, "main" or "test"
+    #[serde(default)]
+    pub network: String,
     pub need_sig: bool,
     pub name: String,
     pub caption: String,
@@ -43,6 +61,14 @@ pub struct QuestionProp {
 pub struct ElectionPropsPub {
     pub pir: String,
     pub end: u32,
+    #[serde(default)]
+    pub close_height: u32,
+    #[serde(default)]
+    pub close_time: u64,
+    #[serde(default)]
+    pub pow_bits: u32,
+    #[serde(default)]
+    pub network: String,
     pub need_sig: bool,
     pub name: String,
     pub caption: String,
@@ -50,15 +76,32 @@ pub struct ElectionPropsPub {
     pub address: String,
     #[serde_as(as = "serde_with::hex::Hex")]
     pub domain: Vec<u8>,
+    // Key that must sign the admin messages of the vote chain
+    #[serde_as(as = "serde_with::hex::Hex")]
+    #[serde(default)]
+    pub authority: Vec<u8>,
 }
 
 pub const ZCV_HRP: &str = "zcv";
 
+// Elections without a network are on the main network
+pub fn network_from_name(name: &str) -> ZCVResult<Network> {
+    match name {
+        "" | "main" => Ok(Network::MainNetwork),
+        "test" => Ok(Network::TestNetwork),
+        _ => Err(anyhow!("Unknown network {name}").into()),
+    }
+}
+
 impl ElectionProps {
     pub fn build(self, secret_seed: &str) -> ZCVResult<ElectionPropsPub> {
         let ElectionProps {
             pir,
             end,
+            close_height,
+            close_time,
+            pow_bits,
+            network,
             need_sig,
             name,
             caption,
@@ -67,13 +110,9 @@ impl ElectionProps {
         } = self;
         let hrp = Hrp::parse(ZCV_HRP).anyhow()?;
 
-        let sk = derive_spending_key(
-            &zcash_protocol::consensus::Network::MainNetwork,
-            secret_seed,
-            0,
-        )
-        .anyhow()?;
+        let sk = derive_spending_key(&network_from_name(&network)?, secret_seed, 0).anyhow()?;
 
+        let authority = authority_key(&sk).to_vec();
         let vk = FullViewingKey::from(&sk);
         let address = vk.address_at(0u64, Scope::External);
         let address =
@@ -81,6 +120,9 @@ impl ElectionProps {
 
         let eph = ElectionPropsHashable {
             end,
+            close_height,
+            close_time,
+            pow_bits,
             need_sig,
             name: name.clone(),
             caption: caption.clone(),
@@ -91,27 +133,71 @@ impl ElectionProps {
         let e = ElectionPropsPub {
             pir,
             end,
+            close_height,
+            close_time,
+            pow_bits,
+            network,
             need_sig,
             name,
             caption,
             questions,
             address,
             domain,
+            authority,
         };
         tracing::info!("{}", serde_json::to_string(&e).unwrap());
         Ok(e)
     }
 }
 
-#[derive(Clone, Encode, Debug)]
+impl ElectionPropsPub {
+    pub fn network(&self) -> ZCVResult<Network> {
+        network_from_name(&self.network)
+    }
+
+    // Can a ballot be included in the block at this vote height
+    // and time?
+    pub fn is_open(&self, vote_height: u32, time: u64) -> bool {
+        (self.close_height == 0 || vote_height <= self.close_height)
+            && (self.close_time == 0 || time < self.close_time)
+    }
+
+    // Last vote height with ballots
+    pub fn closing_vote_height(&self) -> Option<u32> {
+        (self.close_height != 0).then_some(self.close_height)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct ElectionPropsHashable {
     pub end: u32,
+    pub close_height: u32,
+    pub close_time: u64,
+    pub pow_bits: u32,
     pub need_sig: bool,
     pub name: String,
     pub caption: String,
     pub questions: Vec<QuestionProp>,
 }
 
+// The deadline and the stamp are only encoded when they are set,
+// so that the elections without them keep their domain
+impl Encode for ElectionPropsHashable {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.end.encode(encoder)?;
+        self.need_sig.encode(encoder)?;
+        self.name.encode(encoder)?;
+        self.caption.encode(encoder)?;
+        self.questions.encode(encoder)?;
+        if (self.close_height, self.close_time, self.pow_bits) != (0, 0, 0) {
+            self.close_height.encode(encoder)?;
+            self.close_time.encode(encoder)?;
+            self.pow_bits.encode(encoder)?;
+        }
+        Ok(())
+    }
+}
+
 impl ElectionPropsHashable {
     pub fn calculate_domain(&self) -> ZCVResult<Fp> {
         let m = bincode::encode_to_vec(self, bincode::config::standard()).anyhow()?;
@@ -161,7 +247,7 @@ impl UTXO {
 mod tests {
     use crate::{
         pod::ElectionProps,
-        tests::{TEST_ELECTION, TEST_ELECTION_HASH},
+        tests::{TEST_ELECTION, TEST_ELECTION_HASH, TEST_ELECTION_SEED},
     };
 
     #[test]
@@ -174,6 +260,24 @@ mod tests {
         println!("{}", hex::encode(domain));
         assert_eq!(domain, TEST_ELECTION_HASH);
     }
+
+    #[test]
+    fn test_election_rules_domain() {
+        let e: ElectionProps = serde_json::from_value(TEST_ELECTION.clone()).unwrap();
+        let mut domains = vec![TEST_ELECTION_HASH.to_vec()];
+        for (close_height, close_time, pow_bits) in [(3169100, 0, 0), (0, 1767225600, 0), (0, 0, 8)]
+        {
+            let e = ElectionProps {
+                close_height,
+                close_time,
+                pow_bits,
+                ..e.clone()
+            };
+            let epub = e.build(TEST_ELECTION_SEED).unwrap();
+            assert!(!domains.contains(&epub.domain));
+            domains.push(epub.domain);
+        }
+    }
 }
 
 // Mirror type for Fp — adapt based on Fp's actual repr
@@ -0,0 +1,58 @@
+use blake2b_simd::Params;
+
+use crate::tiu;
+
+pub const ZCV_POW_PERSONAL: &[u8] = b"ZCVote_PoWStamp_";
+
+// About 2^28 hashes, a couple of minutes on a phone
+pub const MAX_POW_BITS: u32 = 28;
+
+// Proof of work stamp of a ballot. It is bound to the sighash
+// so that it cannot be reused for another ballot
+fn stamp_hash(sighash: &[u8], nonce: u64) -> [u8; 32] {
+    let hash = Params::new()
+        .personal(ZCV_POW_PERSONAL)
+        .hash_length(32)
+        .to_state()
+        .update(sighash)
+        .update(&nonce.to_le_bytes())
+        .finalize();
+    tiu!(hash.as_bytes())
+}
+
+fn leading_zeros(hash: &[u8]) -> u32 {
+    let mut zeros = 0;
+    for b in hash {
+        zeros += b.leading_zeros();
+        if *b != 0 {
+            break;
+        }
+    }
+    zeros
+}
+
+pub fn check_pow_stamp(sighash: &[u8], nonce: u64, bits: u32) -> bool {
+    bits == 0 || leading_zeros(&stamp_hash(sighash, nonce)) >= bits
+}
+
+// Search for a nonce whose stamp hash starts with `bits` zero bits
+pub fn mint_pow_stamp(sighash: &[u8], bits: u32) -> u64 {
+    (0u64..)
+        .find(|nonce| check_pow_stamp(sighash, *nonce, bits))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pow::{check_pow_stamp, mint_pow_stamp};
+
+    #[test]
+    fn test_pow_stamp() {
+        let sighash = [7u8; 32];
+        let nonce = mint_pow_stamp(&sighash, 12);
+        assert!(check_pow_stamp(&sighash, nonce, 12));
+        // Another ballot needs its own stamp
+        assert!(!(0..16).all(|i| check_pow_stamp(&[i as u8; 32], nonce, 12)));
+        assert!(check_pow_stamp(&sighash, 0, 0));
+    }
+}
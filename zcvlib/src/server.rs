@@ -1,50 +1,98 @@
 #[cfg(feature = "server")]
 use crate::{
     ZCVError, ZCVResult,
-    context::BFTContext,
+    authority::{AdminScope, check_authority_key, verify_admin_message},
+    context::{AdmissionPolicy, BFTContext},
     db::{
-        check_cmx_root, store_ballot,
-        store_cmx_root, store_election, store_election_frontier, store_election_height,
+        check_cmx_root, get_admin_state, get_block_state, list_nullifiers, list_validators,
+        list_vote_elections, store_admin_nonce, store_authority, store_ballot, store_block_state,
+        store_chain_id, store_cmx_root, store_validator, store_vote_election,
+        store_vote_frontier, store_vote_height,
     },
     error::IntoAnyhow,
     pod::ElectionPropsPub,
+    pow::MAX_POW_BITS,
+    server::{
+        cache::VerificationCache,
+        events::{authority_event, ballot_event, election_event, lock_event, validator_event},
+        genesis::GenesisState,
+        listener::AbciListener,
+        merkle::{MerkleFrontier, admin_leaf, election_leaf, nullifier_leaf, validator_leaf},
+        metrics::METRICS,
+        query::{QUERY_ERROR, QUERY_NOT_FOUND, QUERY_OK},
+        snapshot::{SNAPSHOT_INTERVAL, SnapshotRestore, take_snapshot},
+        status::BallotTracker,
+    },
     tiu,
     vote::VK,
-    vote_rpc::{Ballot, Validator, VoteMessage, vote_message::TypeOneof},
+    vote_rpc::{Ballot, SignedMessage, Validator, VoteMessage, vote_message::TypeOneof},
 };
 use anyhow::anyhow;
 use base64::{Engine, prelude::BASE64_STANDARD};
-use blake2b_simd::Params;
 use ff::PrimeField;
 use orchard::tree::MerkleHashOrchard;
 use pasta_curves::Fp;
 use prost::{Message, bytes::Bytes};
 use serde_json::{Value, json};
 use sqlx::{
-    Acquire, Sqlite, SqliteConnection, SqlitePool, Transaction, query,
+    Acquire, Sqlite, SqliteConnection, SqlitePool, Transaction, query, query_as,
 };
-use zcash_trees::warp::{Edge, Hasher, hasher::OrchardHasher};
+use zcash_trees::warp::{Edge, hasher::OrchardHasher};
 use std::{
-    collections::{HashMap, HashSet, hash_map::Entry},
+    cell::Cell,
+    collections::{BTreeMap, HashMap, HashSet},
     net::{Ipv4Addr, SocketAddrV4},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 #[cfg(feature = "server")]
-use tendermint_abci::{Application, ServerBuilder};
+use tendermint_abci::Application;
 #[cfg(feature = "server")]
 use tendermint_proto::{
     abci::{
-        ExecTxResult, RequestCheckTx, RequestFinalizeBlock, RequestInfo, RequestPrepareProposal,
-        RequestProcessProposal, ResponseCheckTx, ResponseCommit, ResponseFinalizeBlock,
-        ResponseInfo, ResponsePrepareProposal, ResponseProcessProposal, ValidatorUpdate,
-        response_process_proposal::ProposalStatus,
+        CheckTxType, Event, ExecTxResult, RequestApplySnapshotChunk, RequestCheckTx, RequestFinalizeBlock,
+        RequestInfo, RequestInitChain, RequestLoadSnapshotChunk, RequestOfferSnapshot,
+        RequestPrepareProposal, RequestProcessProposal, RequestQuery, ResponseApplySnapshotChunk,
+        ResponseCheckTx, ResponseCommit, ResponseFinalizeBlock, ResponseInfo, ResponseInitChain,
+        ResponseListSnapshots, ResponseLoadSnapshotChunk, ResponseOfferSnapshot,
+        ResponsePrepareProposal, ResponseProcessProposal, ResponseQuery, ValidatorUpdate,
+        response_apply_snapshot_chunk, response_process_proposal::ProposalStatus,
     },
     crypto::{PublicKey, public_key::Sum},
+    google::protobuf::Timestamp,
 };
 #[cfg(feature = "server")]
-use tokio::{runtime::Runtime, sync::Mutex};
+use tokio::{
+    runtime::Runtime,
+    sync::{Mutex, watch},
+};
 
+#[cfg(feature = "server")]
+pub mod admin;
+#[cfg(feature = "server")]
+pub mod admission;
+#[cfg(feature = "server")]
+pub mod cache;
+#[cfg(feature = "server")]
+pub mod events;
+#[cfg(feature = "server")]
+pub mod genesis;
+#[cfg(feature = "server")]
+pub mod health;
+#[cfg(feature = "server")]
+pub mod listener;
+#[cfg(feature = "server")]
+pub mod merkle;
+#[cfg(feature = "server")]
+pub mod metrics;
+#[cfg(feature = "server")]
+pub mod query;
+#[cfg(feature = "server")]
+pub mod rest;
+#[cfg(feature = "server")]
+pub mod snapshot;
+#[cfg(feature = "server")]
+pub mod status;
 #[cfg(feature = "server")]
 pub mod rpc;
 
@@ -55,6 +103,9 @@ pub type RPCResult<T> = Result<T, String>;
 #[derive(Clone)]
 pub struct Server {
     state: Arc<Mutex<ServerState>>,
+    // The ABCI handlers are sync. They all run their async code
+    // on this runtime
+    rt: Arc<Runtime>,
 }
 
 impl Server {
@@ -64,25 +115,120 @@ impl Server {
         skip_validation: bool,
     ) -> ZCVResult<Self> {
         let server = ServerState::new(pool, lwd_url, skip_validation).await?;
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .thread_name("zcv-abci")
+            .enable_all()
+            .build()
+            .anyhow()?;
         Ok(Self {
             state: Arc::new(Mutex::new(server)),
+            rt: Arc::new(rt),
         })
     }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.rt.block_on(future)
+    }
+
+    // Lock the state once no block is between finalize_block and commit.
+    // finalize_block keeps its db transaction open until the commit
+    pub async fn wait_for_commit(&self) -> tokio::sync::MutexGuard<'_, ServerState> {
+        loop {
+            let state = self.state.lock().await;
+            if state.db_tx.is_none() {
+                return state;
+            }
+            drop(state);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    // Verify the ballot proofs of a block in parallel, without holding
+    // the server state. The txs are then checked and applied in order
+    // using the verification cache
+    async fn verify_block_ballots(&self, txs: &[Bytes]) -> ZCVResult<()> {
+        let (pool, elections, cache, skip_validation) = {
+            let state = self.state.lock().await;
+            let elections: HashMap<_, _> = state
+                .elections
+                .iter()
+                .map(|(k, e)| (*k, (e.election.clone(), e.domain, e.nf_root)))
+                .collect();
+            (
+                state.pool.clone(),
+                elections,
+                state.check_witnesses_cache.clone(),
+                state.skip_validation,
+            )
+        };
+        let mut ballots: BTreeMap<[u8; 32], Vec<_>> = BTreeMap::new();
+        for ballot in block_ballots(txs) {
+            ballots.entry(ballot.data.domain).or_default().push(ballot);
+        }
+        let mut conn = pool.acquire().await?;
+        for (domain, ballots) in ballots {
+            // Ballots of unknown elections fail when they are applied
+            let Some((election, domain, nf_root)) = elections.get(&domain) else {
+                continue;
+            };
+            ServerState::check_witnesses_batch(
+                &mut conn,
+                election,
+                ballots,
+                *domain,
+                *nf_root,
+                cache.clone(),
+                skip_validation,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+// An election of the vote chain with the anchors of its ballots.
+// Every election has its own cmx tree
+#[derive(Clone)]
+pub struct ElectionState {
+    pub election: ElectionPropsPub,
+    pub domain: Fp,
+    pub nf_root: MerkleHashOrchard,
+    pub cmx_tree: Edge,
+    // A locked election is final
+    pub locked: bool,
 }
 
 pub struct ServerState {
     pub lwd_url: String,
     pub pool: SqlitePool,
-    pub locked: bool,
-    pub election: Option<ElectionPropsPub>,
+    // Elections by domain. Ballots are routed by their domain
+    pub elections: BTreeMap<[u8; 32], ElectionState>,
     pub skip_validation: bool,
-    pub check_witnesses_cache: Arc<parking_lot::Mutex<HashMap<[u8; 32], bool>>>,
-    pub domain: Fp,
-    pub nf_root: MerkleHashOrchard,
-    pub cmx_tree: Edge,
+    pub admission: AdmissionPolicy,
+    pub check_witnesses_cache: Arc<parking_lot::Mutex<VerificationCache>>,
+    pub ballot_tracker: Arc<parking_lot::Mutex<BallotTracker>>,
+    // Wakes up the vote subscriptions after a commit
+    pub block_notify: Arc<watch::Sender<u32>>,
+    // Merkle tree of the spent domain nullifiers
+    pub nf_tree: MerkleFrontier,
+
+    // Admin messages are signed for this chain id, set by InitChain
+    pub chain_id: String,
+    pub authority: Option<[u8; 32]>,
+    pub admin_nonce: u64,
+    // Current validator set: pub_key -> power
+    pub validators: HashMap<Vec<u8>, u32>,
+    // Size of the validator set when the first election was locked
+    pub max_validators: u32,
 
     pub db_tx: Option<Transaction<'static, Sqlite>>,
     pub apphash: [u8; 32],
+    // Time of the last finalized block
+    pub block_time: u64,
+
+    // Blocks between snapshots, 0 to disable them
+    pub snapshot_interval: u32,
+    pub restore: Option<SnapshotRestore>,
 }
 
 impl ServerState {
@@ -91,26 +237,175 @@ impl ServerState {
         lwd_url: &str,
         skip_validation: bool,
     ) -> ZCVResult<Self> {
-        let hasher = OrchardHasher::default();
-        Ok(Self {
+        let mut state = Self {
             pool,
-            check_witnesses_cache: Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            check_witnesses_cache: Arc::new(parking_lot::Mutex::new(VerificationCache::default())),
+            ballot_tracker: Arc::new(parking_lot::Mutex::new(BallotTracker::default())),
+            block_notify: Arc::new(watch::channel(0).0),
             skip_validation,
+            admission: AdmissionPolicy::default(),
             lwd_url: lwd_url.to_string(),
-            locked: false,
-            election: None,
-            domain: Fp::zero(),
-            nf_root: MerkleHashOrchard::from_bytes(&hasher.empty()).unwrap(),
-            cmx_tree: Edge::default(),
+            elections: BTreeMap::new(),
+            nf_tree: MerkleFrontier::default(),
+            chain_id: String::new(),
+            authority: None,
+            admin_nonce: 0,
+            validators: HashMap::new(),
+            max_validators: 0,
             db_tx: None,
             apphash: [0u8; 32],
-        })
+            block_time: 0,
+            snapshot_interval: SNAPSHOT_INTERVAL,
+            restore: None,
+        };
+        state.load().await?;
+        Ok(state)
+    }
+
+    // Restore the state committed by a previous run
+    // so that a restarted node can keep validating ballots
+    pub async fn load(&mut self) -> ZCVResult<()> {
+        let mut conn = self.pool.acquire().await?;
+        self.elections.clear();
+        for (election, nf_root, frontier, locked) in list_vote_elections(&mut conn).await? {
+            let key: [u8; 32] = tiu!(election.domain.clone());
+            let domain = Fp::from_repr(key)
+                .into_option()
+                .ok_or(anyhow!("Invalid election domain"))?;
+            let (nf_root, cmx_tree) = read_roots(&nf_root, &frontier)?;
+            tracing::info!(
+                "Restored election {} with CMX ROOT {}",
+                election.name,
+                hex::encode(cmx_tree.root(&OrchardHasher::default()))
+            );
+            self.elections.insert(
+                key,
+                ElectionState {
+                    election,
+                    domain,
+                    nf_root,
+                    cmx_tree,
+                    locked,
+                },
+            );
+        }
+        self.clear_check_witnesses();
+
+        let (max_validators,): (u32,) =
+            query_as("SELECT max_validators FROM v_state WHERE id = 0")
+                .fetch_one(&mut *conn)
+                .await?;
+        self.max_validators = max_validators;
+        self.validators = list_validators(&mut conn).await?.into_iter().collect();
+
+        let (chain_id, authority, admin_nonce) = get_admin_state(&mut conn).await?;
+        self.chain_id = chain_id;
+        self.authority = authority.try_into().ok();
+        self.admin_nonce = admin_nonce;
+
+        let (_, apphash) = get_block_state(&mut conn).await?;
+        if !apphash.is_empty() {
+            self.apphash = tiu!(apphash);
+        }
+        // The nullifier tree is not stored, it is rebuilt from the ballots
+        self.nf_tree = MerkleFrontier::default();
+        for (dnf, sighash) in list_nullifiers(&mut conn).await? {
+            self.nf_tree.append(nullifier_leaf(&dnf, &sighash));
+        }
+        Ok(())
     }
 }
 
 impl Application for Server {
+    // Report the last committed block so that CometBFT only
+    // replays the blocks we are missing
     fn info(&self, _request: RequestInfo) -> ResponseInfo {
-        ResponseInfo::default()
+        let (block_height, apphash) = self
+            .block_on(async move {
+                let pool = {
+                    let state = self.state.lock().await;
+                    state.pool.clone()
+                };
+                let mut conn = pool.acquire().await?;
+                get_block_state(&mut conn).await
+            })
+            .expect("Cannot read block state");
+        tracing::info!(
+            "info: height {block_height} apphash {}",
+            hex::encode(&apphash)
+        );
+        ResponseInfo {
+            data: "zcv".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            last_block_height: block_height as i64,
+            last_block_app_hash: Bytes::from(apphash),
+            ..ResponseInfo::default()
+        }
+    }
+
+    fn query(&self, request: RequestQuery) -> ResponseQuery {
+        let RequestQuery {
+            path,
+            height,
+            prove,
+            ..
+        } = request;
+        let res = self.block_on(async {
+            // The state and the database must be at the same block
+            let state = self.wait_for_commit().await;
+            state.query(&path, height as u32, prove).await
+        });
+        match res {
+            Ok((height, Some(value), proof_ops)) => ResponseQuery {
+                code: QUERY_OK,
+                key: path.into_bytes().into(),
+                value: serde_json::to_vec(&value).unwrap().into(),
+                proof_ops,
+                height: height as i64,
+                ..ResponseQuery::default()
+            },
+            Ok((height, None, _)) => ResponseQuery {
+                code: QUERY_NOT_FOUND,
+                log: "not found".to_string(),
+                key: path.into_bytes().into(),
+                height: height as i64,
+                ..ResponseQuery::default()
+            },
+            Err(err) => ResponseQuery {
+                code: QUERY_ERROR,
+                log: err.to_string(),
+                ..ResponseQuery::default()
+            },
+        }
+    }
+
+    // Configure the chain from the genesis app_state
+    // An empty app_state leaves the election to be set by txs
+    fn init_chain(&self, request: RequestInitChain) -> ResponseInitChain {
+        let RequestInitChain {
+            chain_id,
+            app_state_bytes,
+            validators,
+            ..
+        } = request;
+        let (validators, app_hash) = self
+            .block_on(async move {
+                let genesis: GenesisState = if app_state_bytes.is_empty() {
+                    GenesisState::default()
+                } else {
+                    serde_json::from_slice(&app_state_bytes)?
+                };
+                let mut state = self.state.lock().await;
+                let validators = state.init_genesis(&chain_id, genesis, validators).await?;
+                Ok::<_, ZCVError>((validators, state.apphash))
+            })
+            .expect("Invalid genesis app_state");
+        // If empty, CometBFT keeps the validators of genesis.json
+        ResponseInitChain {
+            validators,
+            app_hash: Bytes::from(app_hash.to_vec()),
+            ..ResponseInitChain::default()
+        }
     }
 
     // Checks if a tx is structurally correct
@@ -118,9 +413,13 @@ impl Application for Server {
     // But bad txs may be kept for the moment
     fn check_tx(&self, request: RequestCheckTx) -> ResponseCheckTx {
         tracing::info!("check_tx");
-        let RequestCheckTx { mut tx, .. } = request;
-        let rt = Runtime::new().unwrap();
-        let data = rt.block_on(async move {
+        let RequestCheckTx { mut tx, r#type } = request;
+        // Rechecks run after every block on the whole mempool
+        let recheck = r#type == CheckTxType::Recheck as i32;
+        // Stage of the check, for the rejection metrics
+        let stage = Cell::new("decode");
+        let reason = &stage;
+        let data = self.block_on(async move {
             let pool = {
                 let state = self.state.lock().await;
                 state.pool.clone()
@@ -129,63 +428,130 @@ impl Application for Server {
             let msg = VoteMessage::decode(&mut tx)?;
             let msg = msg.type_oneof.ok_or(anyhow!("Must have payload"))?;
             let res = match msg {
-                TypeOneof::SetElection(election) => {
+                TypeOneof::Signed(signed) => {
+                    reason.set("admin");
                     let state = self.state.lock().await;
-                    if state.locked {
-                        anyhow::bail!("Election cannot be added once the blockchain is locked.");
+                    let m = state.open_admin_message(&signed)?;
+                    state.check_admin_policy(&m)?;
+                    match m {
+                        TypeOneof::SetElection(election) => {
+                            let election: ElectionPropsPub =
+                                serde_json::from_str(&election.election)?;
+                            election.domain.clone()
+                        }
+                        TypeOneof::AddValidator(v)
+                        | TypeOneof::RemoveValidator(v)
+                        | TypeOneof::SetValidatorPower(v) => v.pub_key,
+                        TypeOneof::RotateAuthority(a) => a.pub_key,
+                        TypeOneof::Lock(id) => id.domain,
+                        _ => Vec::new(),
                     }
-                    let election: ElectionPropsPub = serde_json::from_str(&election.election)?;
-                    election.domain.clone()
+                }
+                TypeOneof::SetElection(_)
+                | TypeOneof::AddValidator(_)
+                | TypeOneof::RemoveValidator(_)
+                | TypeOneof::SetValidatorPower(_)
+                | TypeOneof::RotateAuthority(_)
+                | TypeOneof::Lock(_) => {
+                    reason.set("admin");
+                    anyhow::bail!("Admin messages must be signed by the election authority");
                 }
                 TypeOneof::Ballot(ballot) => {
                     tracing::info!("check_tx::ballot");
-                    let ballot = from_protobuf(&ballot)?;
-                    let hash = ballot.data.sighash()?;
-                    // Fail on inter block double spend (but pass on intra block
-                    // duplicate because it checks against the db)
-                    let (election, cache, domain, e_nf_root, skip_validation) = {
+                    reason.set("admission");
+                    let decoded = from_protobuf(&ballot)?;
+                    let (
+                        election,
+                        cache,
+                        domain,
+                        e_nf_root,
+                        skip_validation,
+                        block_time,
+                        admission,
+                        tracker,
+                    ) = {
                         let state = self.state.lock().await;
-                        let election = state.election.clone().ok_or(anyhow!("Election not set"))?;
+                        let e = state.ballot_election(&decoded)?;
                         let cache = state.check_witnesses_cache.clone();
                         (
-                            election,
+                            e.election.clone(),
                             cache,
-                            state.domain,
-                            state.nf_root,
+                            e.domain,
+                            e.nf_root,
                             state.skip_validation,
+                            state.block_time,
+                            state.admission.clone(),
+                            state.ballot_tracker.clone(),
                         )
                     };
-                    ServerState::check_ballot(
-                        &mut conn,
-                        &election,
-                        ballot,
-                        domain,
-                        e_nf_root,
-                        cache,
-                        skip_validation,
-                    )
-                    .await?;
-                    tracing::info!("Ballot checked");
-                    hash
-                }
-                TypeOneof::AddValidator(v) => {
-                    let state = self.state.lock().await;
-                    if state.locked {
-                        anyhow::bail!("Validators cannot be added once the blockchain is locked.");
+                    // Ballots in the mempool were admitted already
+                    let ballot = if recheck {
+                        decoded
+                    } else {
+                        admission.admit(&ballot, &election)?
+                    };
+                    let hash = ballot.data.sighash()?;
+                    let checked = async {
+                        // The ballot cannot make it into the next block
+                        // Also drops the late ballots from the mempool on recheck
+                        reason.set("closed");
+                        let (height, _) = get_block_state(&mut conn).await?;
+                        if !election.is_open(election.end + height + 1, block_time) {
+                            anyhow::bail!("Voting is closed");
+                        }
+                        reason.set("invalid");
+                        // Fail on inter block double spend (but pass on intra block
+                        // duplicate because it checks against the db)
+                        if recheck {
+                            // The proofs were verified when the ballot entered the mempool.
+                            // Only check what the last block may have changed
+                            ServerState::recheck_ballot(
+                                &mut conn,
+                                &ballot,
+                                domain,
+                                e_nf_root,
+                                skip_validation,
+                            )
+                            .await?;
+                        } else {
+                            ServerState::check_ballot(
+                                &mut conn,
+                                &election,
+                                ballot,
+                                domain,
+                                e_nf_root,
+                                cache,
+                                skip_validation,
+                            )
+                            .await?;
+                        }
+                        Ok::<_, anyhow::Error>(())
                     }
-                    v.pub_key
-                }
-                TypeOneof::Lock(_) => {
-                    let state = self.state.lock().await;
-                    if state.locked {
-                        anyhow::bail!("Blockchain already locked.");
+                    .await;
+                    match &checked {
+                        Ok(_) if !recheck => tracker.lock().pending(&hash),
+                        Ok(_) => {}
+                        Err(e) => {
+                            if let Some(ZCVError::Duplicate) = e.downcast_ref::<ZCVError>() {
+                                reason.set("duplicate");
+                            }
+                            tracker.lock().rejected(&hash, 0, e.to_string());
+                        }
                     }
-                    Vec::new()
+                    checked?;
+                    tracing::info!("Ballot checked");
+                    hash
                 }
             };
             Ok::<_, anyhow::Error>(res)
         });
 
+        if !recheck {
+            match &data {
+                Ok(_) => METRICS.check_tx_accepted.inc(),
+                Err(_) => METRICS.check_tx_rejected.inc(stage.get()),
+            }
+        }
         match data {
             Ok(data) => ResponseCheckTx {
                 code: 0,
@@ -213,13 +579,24 @@ impl Application for Server {
         // RequestPrepareProposal.max_tx_bytes limit is respected by those
         // transactions returned in ResponsePrepareProposal.txs.
         let RequestPrepareProposal {
-            txs, max_tx_bytes, ..
+            txs,
+            max_tx_bytes,
+            height,
+            time,
+            ..
         } = request;
         let max_tx_bytes = max_tx_bytes as usize;
 
-        let rt = Runtime::new().unwrap();
-        let proposed_txs = rt
+        let proposed_txs = self
             .block_on(async move {
+                let elections: HashMap<_, _> = {
+                    let state = self.state.lock().await;
+                    state
+                        .elections
+                        .iter()
+                        .map(|(k, e)| (*k, e.election.clone()))
+                        .collect()
+                };
                 let mut nfs: HashSet<[u8; 32]> = HashSet::new();
                 let mut proposed_txs = vec![];
                 let mut proposed_len = 0;
@@ -230,6 +607,12 @@ impl Application for Server {
                     let m = msg.type_oneof.expect("VoteMessage must have content");
                     if let TypeOneof::Ballot(ballot) = m {
                         let ballot = from_protobuf(&ballot).anyhow()?;
+                        // Late ballots and ballots of unknown elections
+                        // would get the block rejected
+                        match elections.get(&ballot.data.domain) {
+                            Some(e) if check_voting_open(e, height, time.as_ref()).is_ok() => {}
+                            _ => continue 'next_tx,
+                        }
                         tracing::info!(
                             "Proposing ballot {}...",
                             hex::encode(ballot.data.sighash()?)
@@ -239,6 +622,7 @@ impl Application for Server {
                             // Do not include double spend mempool tx
                             if nfs.contains(&nf) {
                                 tracing::info!("Duplicate tx intra block");
+                                METRICS.proposal_duplicates.inc();
                                 continue 'next_tx;
                             }
                             nfs.insert(nf);
@@ -262,35 +646,23 @@ impl Application for Server {
     // A proposal can come from another node
     // We should not trust it and validate the txs ourself
     fn process_proposal(&self, request: RequestProcessProposal) -> ResponseProcessProposal {
-        let RequestProcessProposal { txs, height, .. } = request;
+        let RequestProcessProposal {
+            txs, height, time, ..
+        } = request;
         // Reject ill formed proposals
-        let rt = tokio::runtime::Runtime::new().unwrap();
-        let res = rt.block_on(async move {
+        let res = self.block_on(async move {
+            self.verify_block_ballots(&txs).await?;
             let state = self.state.lock().await;
+            // Apply the txs like finalize_block, to a copy of the state
+            // so that a tx sees the changes of the admin txs before it,
+            // and do not commit
+            let mut scratch = state.scratch();
             let mut conn = state.pool.acquire().await?;
-            let election = state.election.clone();
-            // Check everything (do the same thing as finalize_block) but do not commit
             let mut db_tx = conn.begin().await?;
-            for (itx, mut tx) in txs.into_iter().enumerate() {
-                let msg = VoteMessage::decode(&mut tx)?;
-                if let Some(TypeOneof::Ballot(ballot)) = msg.type_oneof {
-                    let ballot = from_protobuf(&ballot).anyhow()?;
-                    if let Some(election) = &election {
-                        ServerState::check_witnesses(
-                            &mut db_tx,
-                            election,
-                            &ballot,
-                            state.domain,
-                            state.nf_root,
-                            state.check_witnesses_cache.clone(),
-                            state.skip_validation,
-                        )
-                        .await?;
-                        store_ballot(&mut db_tx, height as u32, itx as u32, ballot).await?;
-                    } else {
-                        anyhow::bail!("No election set");
-                    }
-                }
+            for (itx, tx) in txs.into_iter().enumerate() {
+                scratch
+                    .apply_tx(&mut db_tx, itx as u32, tx, height, time.as_ref())
+                    .await?;
             }
             db_tx.rollback().await?;
             Ok::<_, anyhow::Error>(())
@@ -309,155 +681,88 @@ impl Application for Server {
     // Process the block that was voted on by the validators
     fn finalize_block(&self, request: RequestFinalizeBlock) -> ResponseFinalizeBlock {
         let RequestFinalizeBlock {
-            txs, hash, height, ..
+            txs,
+            hash,
+            height,
+            time,
+            ..
         } = request;
         tracing::info!(
             "Hash {} height {height} {} txs",
             hex::encode(&hash),
             txs.len()
         );
-        let rt = tokio::runtime::Runtime::new().unwrap();
-        let (app_hash, tx_results, validator_updates) = rt
+        let started = Instant::now();
+        let (app_hash, tx_results, validator_updates) = self
             .block_on(async move {
+                // Ballots accepted by process_proposal are already in the cache
+                // and are not verified again
+                self.verify_block_ballots(&txs).await?;
                 let mut state = self.state.lock().await;
                 let orchard_hasher = OrchardHasher::default();
                 let mut validator_updates = vec![];
                 let mut db_tx = state.pool.begin().await?;
-                let apphash = &state.apphash;
                 let mut tx_results = vec![];
-                let new_apphash = if txs.is_empty() {
-                    *apphash
-                } else {
-                    let mut hasher = Params::new()
-                        .personal(b"ZCVote___AppHash")
-                        .hash_length(32)
-                        .key(apphash.as_slice())
-                        .to_state();
-                    for (itx, mut tx) in txs.into_iter().enumerate() {
-                        let tx_copy = tx.clone();
-                        hasher.update(&tx_copy);
-                        let finalize = async {
-                            let msg = VoteMessage::decode(&mut tx)?;
-                            // expect was checked by check_tx
-                            let m = msg.type_oneof.expect("VoteMessage must have content");
-                            match m {
-                                TypeOneof::SetElection(election) => {
-                                    let crate::vote_rpc::Election {
-                                        election,
-                                        nf_root,
-                                        cmx_tree_state,
-                                    } = election;
-                                    let election: ElectionPropsPub =
-                                        serde_json::from_str(&election)?;
-                                    tracing::info!("NF ROOT: {}", hex::encode(&nf_root));
-                                    let (nf_root, cmx_tree) =
-                                        read_roots(&nf_root, &cmx_tree_state)?;
-                                    store_election(&mut db_tx, 0, "", &election, &nf_root.to_bytes(), &cmx_tree_state).await?;
-
-                                    let cmx_root = cmx_tree.root(&orchard_hasher);
-                                    tracing::info!(
-                                        "CMX ROOT: {}",
-                                        hex::encode(cmx_root)
-                                    );
-
-                                    store_cmx_root(&mut db_tx, &cmx_root, election.end)
-                                        .await?;
-                                    let domain =
-                                        Fp::from_repr(tiu!(election.domain.clone())).unwrap();
-
-                                    state.election = Some(election);
-                                    state.domain = domain;
-                                    state.nf_root = nf_root;
-                                    state.cmx_tree = cmx_tree;
-                                }
-                                TypeOneof::Ballot(ballot) => {
-                                    tracing::info!("Incoming ballot");
-                                    let ballot = from_protobuf(&ballot).anyhow()?;
-                                    let hash = ballot.data.sighash()?;
-                                    let election = state
-                                        .election
-                                        .clone()
-                                        .ok_or(anyhow!("Election not set"))?;
-                                    let h = election.end + height as u32;
-                                    tracing::info!(
-                                        "Expected NF ROOT: {}",
-                                        hex::encode(state.nf_root.to_bytes())
-                                    );
-                                    tracing::info!(
-                                        "Expected CMX ROOT: {}",
-                                        hex::encode(state.cmx_tree.root(&orchard_hasher))
-                                    );
-                                    ServerState::check_witnesses(
-                                        &mut db_tx,
-                                        &election,
-                                        &ballot,
-                                        state.domain,
-                                        state.nf_root,
-                                        state.check_witnesses_cache.clone(),
-                                        state.skip_validation,
-                                    )
-                                    .await?;
-                                    for a in ballot.data.actions.iter() {
-                                        let cmx = MerkleHashOrchard::from_bytes(&a.cmx).unwrap();
-                                        state.cmx_tree.append(&orchard_hasher, cmx.to_bytes());
-                                    }
-                                    // This will catch and fail on a double spend because of the UNIQUE dnf
-                                    let id_ballot =
-                                        store_ballot(&mut db_tx, h, itx as u32, ballot).await?;
-                                    if id_ballot.is_none() {
-                                        tracing::info!(
-                                            "Tx already inserted {}",
-                                            hex::encode(&hash)
-                                        );
-                                    }
-                                    store_election_height(&mut db_tx, h).await?;
-                                }
-                                TypeOneof::Lock(_) => {
-                                    state.locked = true;
-                                    query("UPDATE v_state SET locked = 1 WHERE id = 0")
-                                        .execute(&mut *db_tx)
-                                        .await?;
-                                }
-                                TypeOneof::AddValidator(add_validator) => {
-                                    let Validator { pub_key, power } = add_validator;
-                                    let pub_key = PublicKey {
-                                        sum: Some(Sum::Ed25519(pub_key)),
-                                    };
-                                    let v = ValidatorUpdate {
-                                        pub_key: Some(pub_key),
-                                        power: power as i64,
-                                    };
-                                    validator_updates.push(v);
-                                }
-                            }
-                            Ok::<_, anyhow::Error>(())
-                        };
-                        let result = match finalize.await {
-                            Ok(_) => ExecTxResult::default(),
+                for (itx, tx) in txs.into_iter().enumerate() {
+                    let tx_copy = tx.clone();
+                    let finalized = state
+                        .apply_tx(&mut db_tx, itx as u32, tx, height, time.as_ref())
+                        .await;
+                    if let Some(sighash) = ballot_sighash(&tx_copy) {
+                        let mut tracker = state.ballot_tracker.lock();
+                        match &finalized {
+                            Ok(_) => tracker.included(&sighash),
                             Err(error) => {
-                                tracing::info!("Finalization error: {}", error);
-                                ExecTxResult {
-                                    code: 1,
-                                    data: tx_copy,
-                                    log: error.to_string(),
-                                    info: "Error in finalization".to_string(),
-                                    ..ExecTxResult::default()
-                                }
+                                tracker.rejected(&sighash, height as u32, error.to_string())
                             }
-                        };
-                        tx_results.push(result);
+                        }
                     }
-                    tiu!(hasher.finalize().as_bytes())
-                };
+                    let result = match finalized {
+                        Ok(applied) => {
+                            validator_updates.extend(applied.validator_update);
+                            if let Some(actions) = applied.new_actions {
+                                METRICS.ballots.inc();
+                                METRICS.actions.add(actions as u64);
+                                METRICS.latest_ballot_time.set(block_seconds(time.as_ref()));
+                            }
+                            ExecTxResult {
+                                events: vec![applied.event],
+                                ..ExecTxResult::default()
+                            }
+                        }
+                        Err(error) => {
+                            tracing::info!("Finalization error: {}", error);
+                            ExecTxResult {
+                                code: 1,
+                                data: tx_copy,
+                                log: error.to_string(),
+                                info: "Error in finalization".to_string(),
+                                ..ExecTxResult::default()
+                            }
+                        }
+                    };
+                    tx_results.push(result);
+                }
+                // The app hash commits to the state after the block
+                let new_apphash = state.state_hash();
                 let height = height as u32;
-                store_cmx_root(&mut db_tx, &state.cmx_tree.root(&orchard_hasher), height).await?;
-                store_election_frontier(&mut db_tx, &state.cmx_tree).await?;
+                // cmx roots are indexed by vote height like the ballots
+                for (domain, e) in state.elections.iter() {
+                    let vote_height = e.election.end + height;
+                    let cmx_root = e.cmx_tree.root(&orchard_hasher);
+                    store_cmx_root(&mut db_tx, domain, &cmx_root, vote_height).await?;
+                    store_vote_frontier(&mut db_tx, domain, &e.cmx_tree).await?;
+                }
+                // Committed together with the block data
+                store_block_state(&mut db_tx, height, &hash, &new_apphash).await?;
+                state.apphash = new_apphash;
+                state.block_time = block_seconds(time.as_ref());
 
-                state.clear_check_witnesses();
                 state.db_tx = Some(db_tx);
                 Ok::<_, ZCVError>((new_apphash, tx_results, validator_updates))
             })
             .expect("Fatal Failure in FinalizeBlock");
+        METRICS.finalize_block.observe(started.elapsed());
 
         ResponseFinalizeBlock {
             tx_results,
@@ -468,17 +773,140 @@ impl Application for Server {
     }
 
     fn commit(&self) -> ResponseCommit {
-        let rt = tokio::runtime::Runtime::new().unwrap();
-        rt.block_on(async move {
+        self.block_on(async move {
             let mut state = self.state.lock().await;
             if let Some(db_tx) = state.db_tx.take() {
                 db_tx.commit().await?;
             }
+            let mut conn = state.pool.acquire().await?;
+            let (height, _) = get_block_state(&mut conn).await?;
+            state.block_notify.send_replace(height);
+            METRICS.block_height.set(height as u64);
+            if let Some(vote_height) = state.elections.values().map(|e| e.election.end + height).max()
+            {
+                METRICS.vote_height.set(vote_height as u64);
+            }
+            if state.snapshot_interval != 0 && height != 0 && height % state.snapshot_interval == 0 {
+                // Taken in the background so that the next block
+                // does not wait for the state lock
+                let pool = state.pool.clone();
+                tokio::spawn(async move {
+                    // A failed snapshot must not stop the chain
+                    if let Err(e) = take_snapshot(pool, height).await {
+                        tracing::warn!("Snapshot failed: {e}");
+                    }
+                });
+            }
             Ok::<_, ZCVError>(())
         })
         .expect("DB Commit failed");
         ResponseCommit::default()
     }
+
+    fn list_snapshots(&self) -> ResponseListSnapshots {
+        let snapshots = self
+            .block_on(async {
+                let state = self.state.lock().await;
+                state.list_snapshots().await
+            })
+            .unwrap_or_default();
+        ResponseListSnapshots { snapshots }
+    }
+
+    fn offer_snapshot(&self, request: RequestOfferSnapshot) -> ResponseOfferSnapshot {
+        let RequestOfferSnapshot { snapshot, app_hash } = request;
+        self.block_on(async {
+            let mut state = self.state.lock().await;
+            state.offer_snapshot(snapshot, &app_hash)
+        })
+    }
+
+    fn load_snapshot_chunk(&self, request: RequestLoadSnapshotChunk) -> ResponseLoadSnapshotChunk {
+        let RequestLoadSnapshotChunk {
+            height,
+            format,
+            chunk,
+        } = request;
+        let chunk = self
+            .block_on(async {
+                let state = self.state.lock().await;
+                state.load_snapshot_chunk(height, format, chunk).await
+            })
+            .unwrap_or_default();
+        ResponseLoadSnapshotChunk {
+            chunk: chunk.into(),
+        }
+    }
+
+    fn apply_snapshot_chunk(
+        &self,
+        request: RequestApplySnapshotChunk,
+    ) -> ResponseApplySnapshotChunk {
+        let RequestApplySnapshotChunk {
+            index,
+            chunk,
+            sender,
+        } = request;
+        self.block_on(async {
+            let mut state = self.state.lock().await;
+            state
+                .apply_snapshot_chunk(index, chunk.to_vec(), sender)
+                .await
+        })
+        .unwrap_or_else(|e| {
+            tracing::warn!("Snapshot restore failed: {e}");
+            ResponseApplySnapshotChunk {
+                result: response_apply_snapshot_chunk::Result::Abort as i32,
+                ..ResponseApplySnapshotChunk::default()
+            }
+        })
+    }
+}
+
+// Result of a tx applied to the state
+pub struct AppliedTx {
+    pub event: Event,
+    pub validator_update: Option<ValidatorUpdate>,
+    // Actions of a ballot that was not already stored
+    pub new_actions: Option<usize>,
+}
+
+// Ballots of a block, skipping the txs that are not ballots
+// or cannot be decoded. They fail later when they are applied
+fn block_seconds(time: Option<&Timestamp>) -> u64 {
+    time.map(|t| t.seconds.max(0) as u64).unwrap_or_default()
+}
+
+// Ballots are only accepted until the deadline of the election
+fn check_voting_open(
+    election: &ElectionPropsPub,
+    height: i64,
+    time: Option<&Timestamp>,
+) -> anyhow::Result<()> {
+    if !election.is_open(election.end + height as u32, block_seconds(time)) {
+        anyhow::bail!("Voting is closed");
+    }
+    Ok(())
+}
+
+fn ballot_sighash(tx: &Bytes) -> Option<Vec<u8>> {
+    let msg = VoteMessage::decode(tx.as_ref()).ok()?;
+    match msg.type_oneof? {
+        TypeOneof::Ballot(ballot) => from_protobuf(&ballot).ok()?.data.sighash().ok(),
+        _ => None,
+    }
+}
+
+fn block_ballots(txs: &[Bytes]) -> Vec<orchard_vote::Ballot> {
+    txs.iter()
+        .filter_map(|tx| {
+            let msg = VoteMessage::decode(tx.as_ref()).ok()?;
+            match msg.type_oneof? {
+                TypeOneof::Ballot(ballot) => from_protobuf(&ballot).ok(),
+                _ => None,
+            }
+        })
+        .collect()
 }
 
 pub async fn check_dup_nf(conn: &mut SqliteConnection, nf: &[u8]) -> ZCVResult<bool> {
@@ -490,91 +918,710 @@ pub async fn check_dup_nf(conn: &mut SqliteConnection, nf: &[u8]) -> ZCVResult<b
     Ok(exists)
 }
 
+fn to_validator_update(validator: Validator) -> ValidatorUpdate {
+    let Validator { pub_key, power } = validator;
+    let pub_key = PublicKey {
+        sum: Some(Sum::Ed25519(pub_key)),
+    };
+    ValidatorUpdate {
+        pub_key: Some(pub_key),
+        power: power as i64,
+    }
+}
+
+fn from_validator_update(validator: &ValidatorUpdate) -> Option<Validator> {
+    match validator.pub_key.as_ref()?.sum.as_ref()? {
+        Sum::Ed25519(pub_key) => Some(Validator {
+            pub_key: pub_key.clone(),
+            power: validator.power as u32,
+        }),
+        _ => None,
+    }
+}
+
+fn check_validator_key(pub_key: &[u8]) -> ZCVResult<()> {
+    if pub_key.len() != 32 {
+        return Err(ZCVError::Any(anyhow!("Validator key must be an ed25519 key")));
+    }
+    Ok(())
+}
+
 fn read_roots(nf_root: &[u8], cmx_tree: &[u8]) -> ZCVResult<(MerkleHashOrchard, Edge)> {
-    let nf_root = MerkleHashOrchard::from_bytes(&tiu!(nf_root)).unwrap();
-    let cmx_tree = Edge::read(cmx_tree).anyhow()?;
+    let nf_root: [u8; 32] = nf_root
+        .try_into()
+        .map_err(|_| anyhow!("nf root must be 32 bytes"))?;
+    let nf_root = MerkleHashOrchard::from_bytes(&nf_root)
+        .into_option()
+        .ok_or(anyhow!("Invalid nf root"))?;
+    let cmx_tree = if cmx_tree.is_empty() {
+        Edge::default()
+    } else {
+        Edge::read(cmx_tree).anyhow()?
+    };
     Ok((nf_root, cmx_tree))
 }
 
 impl ServerState {
-    pub fn clear_check_witnesses(&mut self) {
-        let mut cache = self.check_witnesses_cache.lock();
-        cache.clear();
-    }
-
-    pub async fn check_witnesses(
+    pub async fn set_election(
+        &mut self,
         conn: &mut SqliteConnection,
-        e: &ElectionPropsPub,
-        ballot: &orchard_vote::Ballot,
-        e_domain: Fp,
-        e_nf_root: MerkleHashOrchard,
-        cache: Arc<parking_lot::Mutex<HashMap<[u8; 32], bool>>>,
-        skip_validation: bool,
+        election: ElectionPropsPub,
+        nf_root: &[u8],
+        cmx_tree_state: &[u8],
     ) -> ZCVResult<()> {
-        let sighash: [u8; 32] = tiu!(ballot.data.sighash().unwrap());
-        {
-            let mut check_witnesses_cache = cache.lock();
-            let cached = check_witnesses_cache.entry(sighash);
-            if let Entry::Occupied(valid) = cached {
-                if !valid.get() {
-                    return Err(ZCVError::Any(anyhow!(
-                        "Ballot previously checked as invalid"
-                    )));
-                }
-                tracing::info!("Witness checked (cached)");
-                return Ok(());
-            }
+        tracing::info!("NF ROOT: {}", hex::encode(nf_root));
+        let (nf_root, cmx_tree) = read_roots(nf_root, cmx_tree_state)?;
+        let key: [u8; 32] = election
+            .domain
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("Invalid election domain"))?;
+        let domain = Fp::from_repr(key)
+            .into_option()
+            .ok_or(anyhow!("Invalid election domain"))?;
+        // An election cannot be replaced once it has ballots
+        if self.elections.contains_key(&key) {
+            return Err(ZCVError::Any(anyhow!("Election already exists")));
+        }
+        if election.close_height != 0 && election.close_height <= election.end {
+            return Err(ZCVError::Any(anyhow!(
+                "close_height must be a vote height after end"
+            )));
         }
+        if election.pow_bits > MAX_POW_BITS {
+            return Err(ZCVError::Any(anyhow!("pow_bits is above {MAX_POW_BITS}")));
+        }
+        store_vote_election(conn, &election, &nf_root.to_bytes(), cmx_tree_state).await?;
 
-        if !skip_validation {
-            let domain = Fp::from_repr(ballot.data.domain)
-                .into_option()
-                .ok_or(anyhow!("Invalid domain"))?;
-            if e_domain != domain {
-                return Err(ZCVError::Any(anyhow!("Ballot has unexpected domain")));
+        let cmx_root = cmx_tree.root(&OrchardHasher::default());
+        tracing::info!("CMX ROOT: {}", hex::encode(cmx_root));
+
+        store_cmx_root(conn, &key, &cmx_root, election.end).await?;
+
+        self.elections.insert(
+            key,
+            ElectionState {
+                election,
+                domain,
+                nf_root,
+                cmx_tree,
+                locked: false,
+            },
+        );
+        // New anchors, the verified ballots no longer apply
+        self.clear_check_witnesses();
+        Ok(())
+    }
+
+    // Copy of the state that process_proposal applies a block to,
+    // and init_genesis the genesis state.
+    // It shares the verification cache with the server state
+    fn scratch(&self) -> ServerState {
+        ServerState {
+            lwd_url: self.lwd_url.clone(),
+            pool: self.pool.clone(),
+            elections: self.elections.clone(),
+            skip_validation: self.skip_validation,
+            admission: self.admission.clone(),
+            check_witnesses_cache: self.check_witnesses_cache.clone(),
+            ballot_tracker: self.ballot_tracker.clone(),
+            block_notify: self.block_notify.clone(),
+            nf_tree: self.nf_tree.clone(),
+            chain_id: self.chain_id.clone(),
+            authority: self.authority,
+            admin_nonce: self.admin_nonce,
+            validators: self.validators.clone(),
+            max_validators: self.max_validators,
+            db_tx: None,
+            apphash: self.apphash,
+            block_time: self.block_time,
+            snapshot_interval: self.snapshot_interval,
+            restore: None,
+        }
+    }
+
+    // Apply a tx of the block at height to the state and the db
+    pub async fn apply_tx(
+        &mut self,
+        db_tx: &mut SqliteConnection,
+        itx: u32,
+        mut tx: Bytes,
+        height: i64,
+        time: Option<&Timestamp>,
+    ) -> anyhow::Result<AppliedTx> {
+        let orchard_hasher = OrchardHasher::default();
+        let msg = VoteMessage::decode(&mut tx)?;
+        let m = msg.type_oneof.ok_or(anyhow!("Must have payload"))?;
+        let m = match m {
+            TypeOneof::Signed(signed) => {
+                let m = self.open_admin_message(&signed)?;
+                self.check_admin_policy(&m)?;
+                self.admin_nonce = signed.nonce;
+                store_admin_nonce(&mut *db_tx, signed.nonce).await?;
+                m
             }
-            let nf_root = MerkleHashOrchard::from_bytes(&ballot.data.anchors.nf)
-                .into_option()
-                .ok_or(anyhow!("Ballot has invalid nf root"))?;
-            if e_nf_root != nf_root {
-                return Err(ZCVError::Any(anyhow!("Ballot has unexpected nf root")));
+            TypeOneof::Ballot(_) => m,
+            _ => anyhow::bail!("Admin messages must be signed by the election authority"),
+        };
+        let mut validator_update = None;
+        let mut new_actions = None;
+        let event = match m {
+            TypeOneof::SetElection(election) => {
+                let crate::vote_rpc::Election {
+                    election,
+                    nf_root,
+                    cmx_tree_state,
+                } = election;
+                let election: ElectionPropsPub = serde_json::from_str(&election)?;
+                let event = election_event(&election);
+                self.set_election(&mut *db_tx, election, &nf_root, &cmx_tree_state)
+                    .await?;
+                event
             }
-            let cmx_root = MerkleHashOrchard::from_bytes(&ballot.data.anchors.cmx)
-                .into_option()
-                .ok_or(anyhow!("Ballot has invalid cmx root"))?;
-            check_cmx_root(conn, &cmx_root.to_bytes()).await?;
+            TypeOneof::Ballot(ballot) => {
+                tracing::info!("Incoming ballot");
+                let ballot = from_protobuf(&ballot).anyhow()?;
+                let hash = ballot.data.sighash()?;
+                let domain = ballot.data.domain;
+                let e = self
+                    .elections
+                    .get_mut(&domain)
+                    .ok_or(anyhow!("Unknown election domain"))?;
+                check_voting_open(&e.election, height, time)?;
+                let h = e.election.end + height as u32;
+                tracing::info!("Expected NF ROOT: {}", hex::encode(e.nf_root.to_bytes()));
+                tracing::info!(
+                    "Expected CMX ROOT: {}",
+                    hex::encode(e.cmx_tree.root(&orchard_hasher))
+                );
+                ServerState::check_witnesses(
+                    &mut *db_tx,
+                    &e.election,
+                    &ballot,
+                    e.domain,
+                    e.nf_root,
+                    self.check_witnesses_cache.clone(),
+                    self.skip_validation,
+                )
+                .await?;
+                let tree_cmxs = ballot
+                    .data
+                    .actions
+                    .iter()
+                    .map(|a| {
+                        MerkleHashOrchard::from_bytes(&a.cmx)
+                            .into_option()
+                            .ok_or(anyhow!("Invalid cmx"))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let dnfs: Vec<_> = ballot.data.actions.iter().map(|a| a.nf.to_vec()).collect();
+                let cmxs: Vec<_> = ballot.data.actions.iter().map(|a| a.cmx.to_vec()).collect();
+                // This will catch and fail on a double spend because of the UNIQUE dnf
+                let id_ballot = store_ballot(&mut *db_tx, h, itx, ballot).await?;
+                store_vote_height(&mut *db_tx, &domain, h).await?;
+                // The cmx tree and the nullifiers are in the app hash. They are
+                // only updated once the ballot is stored
+                if id_ballot.is_none() {
+                    tracing::info!("Tx already inserted {}", hex::encode(&hash));
+                } else {
+                    for cmx in tree_cmxs {
+                        e.cmx_tree.append(&orchard_hasher, cmx.to_bytes());
+                    }
+                    for dnf in dnfs.iter() {
+                        self.nf_tree.append(nullifier_leaf(dnf, &hash));
+                    }
+                    new_actions = Some(dnfs.len());
+                }
+                ballot_event(&hash, &dnfs, &cmxs, h, &e.cmx_tree.root(&orchard_hasher))
+            }
+            TypeOneof::Lock(id) => {
+                let domain = self.election_domain(&id.domain)?;
+                self.lock(&mut *db_tx, domain).await?;
+                lock_event(&domain)
+            }
+            TypeOneof::AddValidator(validator) | TypeOneof::SetValidatorPower(validator) => {
+                let event = validator_event(&validator.pub_key, validator.power);
+                validator_update = Some(self.set_validator(&mut *db_tx, validator).await?);
+                event
+            }
+            TypeOneof::RemoveValidator(validator) => {
+                let validator = Validator {
+                    power: 0,
+                    ..validator
+                };
+                let event = validator_event(&validator.pub_key, 0);
+                validator_update = Some(self.set_validator(&mut *db_tx, validator).await?);
+                event
+            }
+            TypeOneof::RotateAuthority(authority) => {
+                let event = authority_event(&authority.pub_key);
+                let authority = check_authority_key(&authority.pub_key)?;
+                self.set_authority(&mut *db_tx, authority).await?;
+                event
+            }
+            TypeOneof::Signed(_) => unreachable!(),
+        };
+        Ok(AppliedTx {
+            event,
+            validator_update,
+            new_actions,
+        })
+    }
 
-            tracing::info!("Public anchors checked");
-            orchard_vote::validate_ballot(ballot.clone(), e.need_sig, &VK)?;
-            tracing::info!("Witness checked");
-        }
-        {
-            let mut check_witnesses_cache = cache.lock();
-            check_witnesses_cache.insert(sighash, true);
+    // Domain of the election selected by an ElectionId. An empty
+    // domain selects the election of the vote chain if there is only one
+    pub fn election_domain(&self, domain: &[u8]) -> ZCVResult<[u8; 32]> {
+        if domain.is_empty() {
+            let mut domains = self.elections.keys();
+            return match (domains.next(), domains.next()) {
+                (Some(domain), None) => Ok(*domain),
+                (None, _) => Err(ZCVError::Any(anyhow!("No Election Set"))),
+                _ => Err(ZCVError::Any(anyhow!("Election domain required"))),
+            };
         }
-        Ok(())
+        <[u8; 32]>::try_from(domain)
+            .ok()
+            .filter(|d| self.elections.contains_key(d))
+            .ok_or(ZCVError::Any(anyhow!("Unknown election")))
     }
 
-    pub async fn check_ballot(
+    // Whether an election is locked, the validator set is then capped
+    pub fn any_locked(&self) -> bool {
+        self.elections.values().any(|e| e.locked)
+    }
+
+    // Election of a ballot, by the domain of the ballot
+    pub fn ballot_election(&self, ballot: &orchard_vote::Ballot) -> ZCVResult<&ElectionState> {
+        self.elections
+            .get(&ballot.data.domain)
+            .ok_or(ZCVError::Any(anyhow!("Unknown election domain")))
+    }
+
+    pub async fn set_authority(
+        &mut self,
         conn: &mut SqliteConnection,
-        election: &ElectionPropsPub,
-        ballot: orchard_vote::Ballot,
-        e_domain: Fp,
-        e_nf_root: MerkleHashOrchard,
-        cache: Arc<parking_lot::Mutex<HashMap<[u8; 32], bool>>>,
-        skip_validation: bool,
+        authority: [u8; 32],
     ) -> ZCVResult<()> {
-        Self::check_witnesses(
-            conn,
-            election,
-            &ballot,
-            e_domain,
-            e_nf_root,
-            cache,
+        tracing::info!("Election authority: {}", hex::encode(authority));
+        self.authority = Some(authority);
+        store_authority(conn, &authority).await?;
+        Ok(())
+    }
+
+    // Authority of zcv.toml, for chains whose genesis has none
+    pub async fn init_authority(&mut self, authority: [u8; 32]) -> ZCVResult<()> {
+        if self.authority.is_none() {
+            let mut conn = self.pool.acquire().await?;
+            self.set_authority(&mut conn, authority).await?;
+        }
+        Ok(())
+    }
+
+    // Check the authority signature and the nonce of an admin message
+    // and return its content. Admin messages are refused until
+    // the authority is set by genesis or zcv.toml
+    pub fn open_admin_message(&self, signed: &SignedMessage) -> ZCVResult<TypeOneof> {
+        let SignedMessage {
+            message,
+            nonce,
+            signature,
+        } = signed;
+        let m = VoteMessage::decode(message.as_slice())?;
+        let m = m.type_oneof.ok_or(anyhow!("Must have payload"))?;
+        let authority = self.authority.ok_or(anyhow!("No election authority"))?;
+        if *nonce <= self.admin_nonce {
+            return Err(ZCVError::Any(anyhow!("Admin message nonce already used")));
+        }
+        // The signature covers the chain id and the election the message
+        // acts on, so that it cannot be replayed on another chain or,
+        // for a Lock with an empty domain, on another election
+        let domain = match &m {
+            TypeOneof::SetElection(election) => {
+                let election: ElectionPropsPub = serde_json::from_str(&election.election)?;
+                election.domain
+            }
+            TypeOneof::Lock(id) => self.election_domain(&id.domain)?.to_vec(),
+            TypeOneof::Ballot(_) | TypeOneof::Signed(_) => {
+                return Err(ZCVError::Any(anyhow!("Not an admin message")));
+            }
+            _ => vec![],
+        };
+        let scope = AdminScope {
+            chain_id: &self.chain_id,
+            domain: &domain,
+        };
+        verify_admin_message(&authority, &scope, *nonce, message, signature)?;
+        Ok(m)
+    }
+
+    // Once an election is locked, it is final and the validator set cannot
+    // grow. Validators can still be removed, replaced or have their power
+    // changed, and new elections can be added
+    pub fn check_admin_policy(&self, m: &TypeOneof) -> ZCVResult<()> {
+        match m {
+            TypeOneof::Lock(id) => {
+                let domain = self.election_domain(&id.domain)?;
+                if self.elections[&domain].locked {
+                    return Err(ZCVError::Any(anyhow!("Election already locked.")));
+                }
+                Ok(())
+            }
+            TypeOneof::SetElection(election) => {
+                let election: ElectionPropsPub = serde_json::from_str(&election.election)?;
+                if <[u8; 32]>::try_from(election.domain)
+                    .is_ok_and(|domain| self.elections.contains_key(&domain))
+                {
+                    return Err(ZCVError::Any(anyhow!("Election already exists")));
+                }
+                Ok(())
+            }
+            TypeOneof::AddValidator(v) => {
+                check_validator_key(&v.pub_key)?;
+                if v.power == 0 {
+                    return Err(ZCVError::Any(anyhow!("Validator power must not be 0")));
+                }
+                if self.validators.contains_key(&v.pub_key) {
+                    return Err(ZCVError::Any(anyhow!("Validator already exists")));
+                }
+                if self.any_locked() && self.validators.len() as u32 >= self.max_validators {
+                    return Err(ZCVError::Any(anyhow!(
+                        "Validators cannot be added once an election is locked."
+                    )));
+                }
+                Ok(())
+            }
+            TypeOneof::SetValidatorPower(v) => {
+                if !self.validators.contains_key(&v.pub_key) {
+                    return Err(ZCVError::Any(anyhow!("Unknown validator")));
+                }
+                if v.power == 0 {
+                    return Err(ZCVError::Any(anyhow!(
+                        "Validator power must not be 0, use RemoveValidator"
+                    )));
+                }
+                Ok(())
+            }
+            TypeOneof::RemoveValidator(v) => {
+                if !self.validators.contains_key(&v.pub_key) {
+                    return Err(ZCVError::Any(anyhow!("Unknown validator")));
+                }
+                if self.validators.len() == 1 {
+                    return Err(ZCVError::Any(anyhow!("Cannot remove the last validator")));
+                }
+                Ok(())
+            }
+            TypeOneof::RotateAuthority(a) => {
+                check_authority_key(&a.pub_key)?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Update the validator set and return the update for CometBFT
+    pub async fn set_validator(
+        &mut self,
+        conn: &mut SqliteConnection,
+        validator: Validator,
+    ) -> ZCVResult<ValidatorUpdate> {
+        tracing::info!(
+            "Validator {} power {}",
+            hex::encode(&validator.pub_key),
+            validator.power
+        );
+        store_validator(conn, &validator.pub_key, validator.power).await?;
+        if validator.power == 0 {
+            self.validators.remove(&validator.pub_key);
+        } else {
+            self.validators
+                .insert(validator.pub_key.clone(), validator.power);
+        }
+        Ok(to_validator_update(validator))
+    }
+
+    // The first lock caps the validator set to its current size
+    pub async fn lock(&mut self, conn: &mut SqliteConnection, domain: [u8; 32]) -> ZCVResult<()> {
+        if !self.any_locked() {
+            self.max_validators = self.validators.len() as u32;
+            query("UPDATE v_state SET max_validators = ?1 WHERE id = 0")
+                .bind(self.max_validators)
+                .execute(&mut *conn)
+                .await?;
+        }
+        let e = self
+            .elections
+            .get_mut(&domain)
+            .ok_or(anyhow!("Unknown election"))?;
+        e.locked = true;
+        query("UPDATE v_elections SET locked = 1 WHERE domain = ?1")
+            .bind(domain.as_slice())
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    // Apply the genesis app_state. InitChain is not followed by a Commit,
+    // so the changes are committed right away. They are idempotent in case
+    // CometBFT calls InitChain again after a crash
+    // The validators of genesis.json are used when app_state has none
+    pub async fn init_genesis(
+        &mut self,
+        chain_id: &str,
+        genesis: GenesisState,
+        genesis_validators: Vec<ValidatorUpdate>,
+    ) -> ZCVResult<Vec<ValidatorUpdate>> {
+        let GenesisState {
+            election,
+            nf_root,
+            cmx_tree_state,
+            validators,
+            locked,
+            authority,
+        } = genesis;
+        let validators: Vec<_> = validators
+            .into_iter()
+            .map(|v| Validator {
+                pub_key: v.pub_key,
+                power: v.power,
+            })
+            .collect();
+        for v in validators.iter() {
+            check_validator_key(&v.pub_key)?;
+            if v.power == 0 {
+                return Err(ZCVError::Any(anyhow!(
+                    "Genesis validator power must be positive"
+                )));
+            }
+        }
+        // The state only changes once the db is committed
+        let mut state = self.scratch();
+        let mut db_tx = self.pool.begin().await?;
+        state.chain_id = chain_id.to_string();
+        store_chain_id(&mut db_tx, chain_id).await?;
+        let validator_updates = if validators.is_empty() {
+            // Keep the validators of genesis.json
+            for v in genesis_validators.iter().filter_map(from_validator_update) {
+                state.set_validator(&mut db_tx, v).await?;
+            }
+            vec![]
+        } else {
+            let mut validator_updates = vec![];
+            for v in validators {
+                validator_updates.push(state.set_validator(&mut db_tx, v).await?);
+            }
+            validator_updates
+        };
+        // The authority defaults to the one of the genesis election
+        let authority = match &election {
+            Some(election) if authority.is_empty() => election.authority.clone(),
+            _ => authority,
+        };
+        if !authority.is_empty() {
+            let authority = authority
+                .try_into()
+                .map_err(|_| anyhow!("Invalid genesis authority"))?;
+            state.set_authority(&mut db_tx, authority).await?;
+        }
+        if let Some(election) = election {
+            let domain = election.domain.clone();
+            // Already set if InitChain is called again
+            if !state.elections.keys().any(|d| *d == domain[..]) {
+                tracing::info!("Genesis election: {}", election.name);
+                state
+                    .set_election(&mut db_tx, election, &nf_root, &cmx_tree_state)
+                    .await?;
+            }
+            if locked {
+                let domain = state.election_domain(&domain)?;
+                state.lock(&mut db_tx, domain).await?;
+            }
+        } else if locked {
+            return Err(ZCVError::Any(anyhow!("locked requires the genesis election")));
+        }
+        state.apphash = state.state_hash();
+        store_block_state(&mut db_tx, 0, &[], &state.apphash).await?;
+        db_tx.commit().await?;
+        *self = state;
+        Ok(validator_updates)
+    }
+
+    // Leaves of the app hash: the root of the nullifier tree, then
+    // the domain, nf root, cmx root and lock of every election (by domain),
+    // the validators (by key) and the admin state
+    pub fn state_leaves(&self) -> Vec<[u8; 32]> {
+        let hasher = OrchardHasher::default();
+        let mut leaves = vec![self.nf_tree.root()];
+        for (domain, e) in self.elections.iter() {
+            leaves.push(election_leaf(
+                domain,
+                &e.nf_root.to_bytes(),
+                &e.cmx_tree.root(&hasher),
+                e.locked,
+            ));
+        }
+        let mut validators: Vec<_> = self.validators.iter().collect();
+        validators.sort();
+        for (pub_key, power) in validators {
+            leaves.push(validator_leaf(pub_key, *power));
+        }
+        leaves.push(admin_leaf(
+            &self.chain_id,
+            self.max_validators,
+            &self.authority.unwrap_or_default(),
+            self.admin_nonce,
+        ));
+        leaves
+    }
+
+    // Merkle root of the state, query results are proven against it
+    pub fn state_hash(&self) -> [u8; 32] {
+        merkle::root(&self.state_leaves())
+    }
+
+    pub fn clear_check_witnesses(&mut self) {
+        let mut cache = self.check_witnesses_cache.lock();
+        cache.clear();
+    }
+
+    pub async fn check_witnesses(
+        conn: &mut SqliteConnection,
+        e: &ElectionPropsPub,
+        ballot: &orchard_vote::Ballot,
+        e_domain: Fp,
+        e_nf_root: MerkleHashOrchard,
+        cache: Arc<parking_lot::Mutex<VerificationCache>>,
+        skip_validation: bool,
+    ) -> ZCVResult<()> {
+        let key = VerificationCache::key(ballot)?;
+        if cache.lock().contains(&key) {
+            tracing::info!("Witness checked (cached)");
+            return Ok(());
+        }
+
+        if !skip_validation {
+            Self::check_anchors(conn, ballot, e_domain, e_nf_root).await?;
+            tracing::info!("Public anchors checked");
+            let started = Instant::now();
+            orchard_vote::validate_ballot(ballot.clone(), e.need_sig, &VK)?;
+            METRICS.proof_verification.observe(started.elapsed());
+            tracing::info!("Witness checked");
+        }
+        cache.lock().insert(key);
+        Ok(())
+    }
+
+    pub async fn check_anchors(
+        conn: &mut SqliteConnection,
+        ballot: &orchard_vote::Ballot,
+        e_domain: Fp,
+        e_nf_root: MerkleHashOrchard,
+    ) -> ZCVResult<()> {
+        let domain = Fp::from_repr(ballot.data.domain)
+            .into_option()
+            .ok_or(anyhow!("Invalid domain"))?;
+        if e_domain != domain {
+            return Err(ZCVError::Any(anyhow!("Ballot has unexpected domain")));
+        }
+        let nf_root = MerkleHashOrchard::from_bytes(&ballot.data.anchors.nf)
+            .into_option()
+            .ok_or(anyhow!("Ballot has invalid nf root"))?;
+        if e_nf_root != nf_root {
+            return Err(ZCVError::Any(anyhow!("Ballot has unexpected nf root")));
+        }
+        let cmx_root = MerkleHashOrchard::from_bytes(&ballot.data.anchors.cmx)
+            .into_option()
+            .ok_or(anyhow!("Ballot has invalid cmx root"))?;
+        check_cmx_root(conn, &e_domain.to_repr(), &cmx_root.to_bytes()).await?;
+        Ok(())
+    }
+
+    // Verify the proofs of the ballots of a block in parallel and cache the
+    // valid ones. Invalid ballots are left out of the cache so that
+    // check_witnesses reports their error when the txs are applied in order
+    pub async fn check_witnesses_batch(
+        conn: &mut SqliteConnection,
+        e: &ElectionPropsPub,
+        ballots: Vec<orchard_vote::Ballot>,
+        e_domain: Fp,
+        e_nf_root: MerkleHashOrchard,
+        cache: Arc<parking_lot::Mutex<VerificationCache>>,
+        skip_validation: bool,
+    ) -> ZCVResult<()> {
+        if skip_validation {
+            return Ok(());
+        }
+        let mut tasks = vec![];
+        for ballot in ballots {
+            let Ok(key) = VerificationCache::key(&ballot) else {
+                continue;
+            };
+            if cache.lock().contains(&key) {
+                continue;
+            }
+            if Self::check_anchors(conn, &ballot, e_domain, e_nf_root)
+                .await
+                .is_err()
+            {
+                continue;
+            }
+            let need_sig = e.need_sig;
+            tasks.push(tokio::task::spawn_blocking(move || {
+                let started = Instant::now();
+                let valid = orchard_vote::validate_ballot(ballot, need_sig, &VK).is_ok();
+                METRICS.proof_verification.observe(started.elapsed());
+                (key, valid)
+            }));
+        }
+        tracing::info!("Verifying {} ballots", tasks.len());
+        for r in futures::future::join_all(tasks).await {
+            let (key, valid) = r.anyhow()?;
+            if valid {
+                cache.lock().insert(key);
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn check_ballot(
+        conn: &mut SqliteConnection,
+        election: &ElectionPropsPub,
+        ballot: orchard_vote::Ballot,
+        e_domain: Fp,
+        e_nf_root: MerkleHashOrchard,
+        cache: Arc<parking_lot::Mutex<VerificationCache>>,
+        skip_validation: bool,
+    ) -> ZCVResult<()> {
+        Self::check_witnesses(
+            conn,
+            election,
+            &ballot,
+            e_domain,
+            e_nf_root,
+            cache,
             skip_validation,
         )
         .await?;
-        for a in ballot.data.actions {
+        Self::check_nullifiers(conn, &ballot).await
+    }
+
+    pub async fn recheck_ballot(
+        conn: &mut SqliteConnection,
+        ballot: &orchard_vote::Ballot,
+        e_domain: Fp,
+        e_nf_root: MerkleHashOrchard,
+        skip_validation: bool,
+    ) -> ZCVResult<()> {
+        if !skip_validation {
+            Self::check_anchors(conn, ballot, e_domain, e_nf_root).await?;
+        }
+        Self::check_nullifiers(conn, ballot).await
+    }
+
+    pub async fn check_nullifiers(
+        conn: &mut SqliteConnection,
+        ballot: &orchard_vote::Ballot,
+    ) -> ZCVResult<()> {
+        for a in ballot.data.actions.iter() {
             tracing::info!("Action NF: {}", hex::encode(a.nf));
             let exists = check_dup_nf(conn, a.nf.as_slice()).await?;
             if exists {
@@ -639,22 +1686,1260 @@ pub fn from_protobuf(ballot: &Ballot) -> std::io::Result<orchard_vote::Ballot> {
     Ok(ballot)
 }
 
+// Serve the ABCI app until shutdown resolves, then wait for
+// the block in progress to be committed
 pub async fn run_cometbft_app(
     context: Arc<tokio::sync::Mutex<BFTContext>>,
     port: u16,
+    shutdown: impl Future<Output = ()>,
 ) -> ZCVResult<()> {
-    let (pool, lwd_url, skip_validation) = {
+    let (pool, lwd_url, skip_validation, authority, admission, ballot_tracker, block_notify) = {
         let c = context.lock().await;
         (
             c.context.pool.clone(),
             c.context.lwd_url.clone(),
             c.skip_validation,
+            c.authority,
+            c.admission.clone(),
+            c.ballot_tracker.clone(),
+            c.block_notify.clone(),
         )
     };
+    {
+        let mut conn = pool.acquire().await?;
+        METRICS.load(&mut conn).await?;
+    }
     let app = Server::new(pool, &lwd_url, skip_validation).await?;
-    let server = ServerBuilder::new(1_000_000)
-        .bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port), app)
+    {
+        let mut state = app.state.lock().await;
+        if let Some(authority) = authority {
+            state.init_authority(authority).await?;
+        }
+        state.admission = admission;
+        state.ballot_tracker = ballot_tracker;
+        state.block_notify = block_notify;
+    }
+    let listener = AbciListener::bind(
+        SocketAddrV4::new(Ipv4Addr::LOCALHOST, port).into(),
+        app.clone(),
+    )
+    .anyhow()?;
+    shutdown.await;
+    tracing::info!("Shutting down, waiting for the block in progress");
+    let state = app.wait_for_commit().await;
+    // Disconnect CometBFT before releasing the state so that no new block starts
+    listener.stop();
+    drop(state);
+    // Its runtime cannot be dropped from async code
+    tokio::task::spawn_blocking(move || drop(app))
+        .await
         .anyhow()?;
-    server.listen().anyhow()?;
+    tracing::info!("ABCI app stopped");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use prost::{Message, bytes::Bytes};
+    use sqlx::SqlitePool;
+    use tendermint_abci::Application;
+    use serde_json::json;
+    use tendermint_proto::abci::{
+        CheckTxType, RequestApplySnapshotChunk, RequestCheckTx, RequestFinalizeBlock, RequestInfo,
+        RequestInitChain, RequestLoadSnapshotChunk, RequestOfferSnapshot, RequestProcessProposal,
+        RequestQuery, ResponseQuery, response_apply_snapshot_chunk, response_offer_snapshot,
+        response_process_proposal::ProposalStatus,
+    };
+    use tokio::runtime::Runtime;
+    use zcash_protocol::consensus::Network;
+    use zcash_trees::warp::{Edge, hasher::OrchardHasher};
+
+    use crate::{
+        authority::{AdminScope, authority_key_from_seed, sign_admin_message},
+        context::{AdmissionPolicy, BFTContext},
+        db::{create_schema, drop_schema, store_chain_id, store_cmx_root},
+        pod::{ElectionProps, ElectionPropsPub},
+        pow::{check_pow_stamp, mint_pow_stamp},
+        server::{
+            Server, ServerState,
+            cache::VerificationCache,
+            events::{EVENT_BALLOT, EVENT_ELECTION, EVENT_LOCK},
+            genesis::{GenesisState, GenesisValidator},
+            merkle::{election_leaf, nullifier_leaf, verify_proof_ops},
+            query::{QUERY_ERROR, QUERY_NOT_FOUND, QUERY_OK},
+            status::TrackedBallot,
+        },
+        tests::{TEST_ELECTION, TEST_ELECTION_SEED, TEST_SEED, get_connection, test_setup},
+        tiu,
+        vote::mint,
+        vote_rpc::{
+            Authority, Ballot, Election, ElectionId, SignedMessage, Validator, VoteMessage,
+            vote_message::TypeOneof,
+        },
+    };
+
+    async fn test_pool() -> Result<SqlitePool> {
+        test_pool_at("server-test.db").await
+    }
+
+    // Runtime, server database and N ballots minted for the test election
+    fn test_fixture<const N: usize>() -> Result<(Runtime, SqlitePool, [orchard_vote::Ballot; N])> {
+        let rt = Runtime::new()?;
+        let pool = rt.block_on(test_pool())?;
+        let ballots = rt.block_on(async {
+            let mut conn = get_connection().await?;
+            test_setup(&mut conn).await?;
+            let mut ballots = vec![];
+            for _ in 0..N {
+                ballots.push(mint(&Network::MainNetwork, &mut conn, 0, 1000).await?);
+            }
+            Ok::<_, anyhow::Error>(ballots)
+        })?;
+        let mut ballots = ballots.into_iter();
+        Ok((rt, pool, std::array::from_fn(|_| ballots.next().unwrap())))
+    }
+
+    async fn test_pool_at(db_path: &str) -> Result<SqlitePool> {
+        let ctx = BFTContext::new(db_path, "", 0, true).await?;
+        let mut conn = ctx.connect().await?;
+        drop_schema(&mut conn).await?;
+        create_schema(&mut conn).await?;
+        Ok(ctx.context.pool)
+    }
+
+    const TEST_CHAIN_ID: &str = "zcv-test";
+
+    // Server with the authority of the test election, as set by zcv.toml
+    async fn test_server(pool: SqlitePool) -> Result<Server> {
+        store_chain_id(&mut *pool.acquire().await?, TEST_CHAIN_ID).await?;
+        let app = Server::new(pool, "", true).await?;
+        let authority = authority_key_from_seed(&Network::MainNetwork, TEST_ELECTION_SEED)?;
+        app.state.lock().await.init_authority(authority).await?;
+        Ok(app)
+    }
+
+    fn to_tx(m: TypeOneof) -> Bytes {
+        let m = VoteMessage {
+            type_oneof: Some(m),
+        };
+        m.encode_to_vec().into()
+    }
+
+    fn signed_tx(seed: &str, nonce: u64, m: TypeOneof) -> Result<Bytes> {
+        signed_tx_for(TEST_CHAIN_ID, seed, nonce, m)
+    }
+
+    // Signed for a chain and the election the message acts on.
+    // A Lock without a domain is for the test election
+    fn signed_tx_for(chain_id: &str, seed: &str, nonce: u64, m: TypeOneof) -> Result<Bytes> {
+        let domain = match &m {
+            TypeOneof::SetElection(election) => {
+                serde_json::from_str::<ElectionPropsPub>(&election.election)?.domain
+            }
+            TypeOneof::Lock(id) if id.domain.is_empty() => test_election()?.0.domain,
+            TypeOneof::Lock(id) => id.domain.clone(),
+            _ => vec![],
+        };
+        let scope = AdminScope {
+            chain_id,
+            domain: &domain,
+        };
+        let message = VoteMessage {
+            type_oneof: Some(m),
+        }
+        .encode_to_vec();
+        let signature = sign_admin_message(&Network::MainNetwork, seed, &scope, nonce, &message)?;
+        Ok(to_tx(TypeOneof::Signed(SignedMessage {
+            message,
+            nonce,
+            signature: signature.to_vec(),
+        })))
+    }
+
+    fn ballot_tx(ballot: &orchard_vote::Ballot) -> Result<Bytes> {
+        let mut bytes = vec![];
+        ballot.write(&mut bytes)?;
+        Ok(to_tx(TypeOneof::Ballot(Ballot {
+            ballot: bytes,
+            ..Ballot::default()
+        })))
+    }
+
+    fn test_election() -> Result<(ElectionPropsPub, Vec<u8>)> {
+        let e = TEST_ELECTION;
+        let e: ElectionProps = serde_json::from_value(e.clone())?;
+        let e = e.build(TEST_ELECTION_SEED)?;
+        let mut cmx_tree_state = vec![];
+        Edge::default().write(&mut cmx_tree_state)?;
+        Ok((e, cmx_tree_state))
+    }
+
+    fn set_election() -> Result<TypeOneof> {
+        let (e, cmx_tree_state) = test_election()?;
+        Ok(TypeOneof::SetElection(Election {
+            election: serde_json::to_string(&e)?,
+            nf_root: vec![0u8; 32],
+            cmx_tree_state,
+        }))
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_restart_mid_election() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+
+        let app = rt.block_on(test_server(pool.clone()))?;
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![
+                signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?,
+                signed_tx(TEST_ELECTION_SEED, 2, TypeOneof::Lock(ElectionId::default()))?,
+            ],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let (domain, nf_root, cmx_root, apphash) = rt.block_on(async {
+            let state = app.state.lock().await;
+            let e = state.elections.values().next().unwrap();
+            (
+                e.domain,
+                e.nf_root,
+                e.cmx_tree.root(&OrchardHasher::default()),
+                state.apphash,
+            )
+        });
+        drop(app);
+
+        // Simulate a restart of vote-cometbft
+        let app = rt.block_on(Server::new(pool, "", true))?;
+        rt.block_on(async {
+            let state = app.state.lock().await;
+            assert_eq!(state.elections.len(), 1);
+            assert!(state.any_locked());
+            let e = state.elections.values().next().unwrap();
+            assert_eq!(e.domain, domain);
+            assert_eq!(e.nf_root, nf_root);
+            assert_eq!(e.cmx_tree.root(&OrchardHasher::default()), cmx_root);
+            assert_eq!(state.apphash, apphash);
+            assert_eq!(state.admin_nonce, 2);
+        });
+        let info = app.info(RequestInfo::default());
+        assert_eq!(info.last_block_height, 1);
+        assert_eq!(info.last_block_app_hash.as_ref(), apphash.as_slice());
+        Ok(())
+    }
+    #[test]
+    #[serial_test::serial]
+    fn test_invalid_nf_root() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+        let app = rt.block_on(test_server(pool))?;
+        let (e, cmx_tree_state) = test_election()?;
+        // Wrong length, then not a field element
+        for (nonce, nf_root) in [(1, vec![0u8; 31]), (2, vec![0xFFu8; 32])] {
+            let set_election = TypeOneof::SetElection(Election {
+                election: serde_json::to_string(&e)?,
+                nf_root,
+                cmx_tree_state: cmx_tree_state.clone(),
+            });
+            let res = app.finalize_block(RequestFinalizeBlock {
+                txs: vec![signed_tx(TEST_ELECTION_SEED, nonce, set_election)?],
+                height: nonce as i64,
+                ..RequestFinalizeBlock::default()
+            });
+            assert!(res.tx_results[0].log.contains("nf root"));
+            app.commit();
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_init_chain_from_genesis() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+        let (e, cmx_tree_state) = test_election()?;
+        let app_state = json!({
+            "election": e,
+            "nf_root": hex::encode([0u8; 32]),
+            "cmx_tree_state": hex::encode(&cmx_tree_state),
+            "validators": [{ "pub_key": hex::encode([1u8; 32]), "power": 10 }],
+            "locked": true,
+        });
+
+        let app = rt.block_on(Server::new(pool.clone(), "", true))?;
+        let rep = app.init_chain(RequestInitChain {
+            chain_id: TEST_CHAIN_ID.to_string(),
+            app_state_bytes: serde_json::to_vec(&app_state)?.into(),
+            ..RequestInitChain::default()
+        });
+        assert_eq!(rep.validators.len(), 1);
+        assert_eq!(rep.validators[0].power, 10);
+        drop(app);
+
+        // The genesis state must be committed without a Commit
+        let app = rt.block_on(Server::new(pool, "", true))?;
+        rt.block_on(async {
+            let state = app.state.lock().await;
+            let election = &state.elections.values().next().unwrap().election;
+            assert_eq!(election.domain, e.domain);
+            assert!(state.any_locked());
+            // The authority of the election, as app_state has none
+            assert_eq!(state.authority.map(|a| a.to_vec()), Some(e.authority.clone()));
+            assert_eq!(state.chain_id, TEST_CHAIN_ID);
+        });
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_init_genesis_rejected() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+        let app = rt.block_on(Server::new(pool, "", true))?;
+        rt.block_on(async {
+            let mut state = app.state.lock().await;
+            let validator = |pub_key: Vec<u8>, power: u32| GenesisState {
+                validators: vec![GenesisValidator { pub_key, power }],
+                ..GenesisState::default()
+            };
+            for genesis in [
+                validator(vec![1u8; 31], 10),
+                validator(vec![1u8; 32], 0),
+                // Fails after the validators are stored
+                GenesisState {
+                    locked: true,
+                    ..validator(vec![1u8; 32], 10)
+                },
+            ] {
+                let r = state.init_genesis(TEST_CHAIN_ID, genesis, vec![]).await;
+                assert!(r.is_err());
+                assert!(state.validators.is_empty());
+                assert!(state.chain_id.is_empty());
+            }
+            // Nor in the db
+            let state = ServerState::new(state.pool.clone(), "", true).await?;
+            assert!(state.validators.is_empty());
+            assert!(state.chain_id.is_empty());
+            Ok::<_, anyhow::Error>(())
+        })?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_admin_message_authentication() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+        rt.block_on(async { store_chain_id(&mut *pool.acquire().await?, TEST_CHAIN_ID).await })?;
+        let app = rt.block_on(Server::new(pool, "", true))?;
+        let check = |tx: Bytes| {
+            app.check_tx(RequestCheckTx {
+                tx,
+                ..RequestCheckTx::default()
+            })
+            .code
+        };
+
+        // No authority yet, even for a correctly signed election
+        assert_ne!(check(signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?), 0);
+        rt.block_on(async {
+            let authority = authority_key_from_seed(&Network::MainNetwork, TEST_ELECTION_SEED)?;
+            app.state.lock().await.init_authority(authority).await
+        })?;
+
+        // Unsigned or signed by someone else
+        assert_ne!(check(to_tx(set_election()?)), 0);
+        assert_ne!(check(signed_tx(TEST_SEED, 1, set_election()?)?), 0);
+        assert_eq!(check(signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?), 0);
+
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+
+        // Replayed nonce
+        assert_ne!(check(signed_tx(TEST_ELECTION_SEED, 1, TypeOneof::Lock(ElectionId::default()))?), 0);
+        assert_ne!(check(signed_tx(TEST_SEED, 2, TypeOneof::Lock(ElectionId::default()))?), 0);
+        // Signed for another chain or another election
+        let lock = || TypeOneof::Lock(ElectionId::default());
+        assert_ne!(check(signed_tx_for("zcv-other", TEST_ELECTION_SEED, 2, lock())?), 0);
+        let message = VoteMessage {
+            type_oneof: Some(lock()),
+        }
+        .encode_to_vec();
+        let scope = AdminScope {
+            chain_id: TEST_CHAIN_ID,
+            domain: &[0u8; 32],
+        };
+        let signature =
+            sign_admin_message(&Network::MainNetwork, TEST_ELECTION_SEED, &scope, 2, &message)?;
+        let other_election = to_tx(TypeOneof::Signed(SignedMessage {
+            message,
+            nonce: 2,
+            signature: signature.to_vec(),
+        }));
+        assert_ne!(check(other_election), 0);
+        assert_eq!(check(signed_tx(TEST_ELECTION_SEED, 2, TypeOneof::Lock(ElectionId::default()))?), 0);
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_validator_set_after_lock() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+        let (e, cmx_tree_state) = test_election()?;
+        let app_state = json!({
+            "election": e,
+            "nf_root": hex::encode([0u8; 32]),
+            "cmx_tree_state": hex::encode(&cmx_tree_state),
+            "validators": [
+                { "pub_key": hex::encode([1u8; 32]), "power": 10 },
+                { "pub_key": hex::encode([2u8; 32]), "power": 10 },
+            ],
+            "locked": true,
+        });
+        let app = rt.block_on(Server::new(pool.clone(), "", true))?;
+        app.init_chain(RequestInitChain {
+            chain_id: TEST_CHAIN_ID.to_string(),
+            app_state_bytes: serde_json::to_vec(&app_state)?.into(),
+            ..RequestInitChain::default()
+        });
+
+        let validator = |k: u8, power: u32| Validator {
+            pub_key: vec![k; 32],
+            power,
+        };
+        let rep = app.finalize_block(RequestFinalizeBlock {
+            txs: vec![
+                // Replace validator 2 by validator 3
+                signed_tx(TEST_ELECTION_SEED, 1, TypeOneof::RemoveValidator(validator(2, 0)))?,
+                signed_tx(TEST_ELECTION_SEED, 2, TypeOneof::AddValidator(validator(3, 5)))?,
+                // The set cannot grow after the lock
+                signed_tx(TEST_ELECTION_SEED, 3, TypeOneof::AddValidator(validator(4, 5)))?,
+                signed_tx(TEST_ELECTION_SEED, 3, TypeOneof::SetValidatorPower(validator(1, 20)))?,
+            ],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let codes: Vec<_> = rep.tx_results.iter().map(|r| r.code).collect();
+        assert_eq!(codes, [0, 0, 1, 0]);
+        let updates: Vec<_> = rep.validator_updates.iter().map(|v| v.power).collect();
+        assert_eq!(updates, [0, 5, 20]);
+        drop(app);
+
+        let app = rt.block_on(Server::new(pool, "", true))?;
+        rt.block_on(async {
+            let state = app.state.lock().await;
+            assert_eq!(state.max_validators, 2);
+            assert_eq!(state.validators.len(), 2);
+            assert_eq!(state.validators.get(&vec![1u8; 32]), Some(&20));
+            assert_eq!(state.validators.get(&vec![3u8; 32]), Some(&5));
+        });
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_process_proposal_admin_effects() -> Result<()> {
+        let (rt, pool, [ballot]) = test_fixture()?;
+        let app = rt.block_on(test_server(pool))?;
+        let process = |txs: Vec<Bytes>| {
+            app.process_proposal(RequestProcessProposal {
+                txs,
+                height: 2,
+                ..RequestProcessProposal::default()
+            })
+            .status
+        };
+        let accept = ProposalStatus::Accept as i32;
+        let reject = ProposalStatus::Reject as i32;
+        let validator = |k: u8, power: u32| Validator {
+            pub_key: vec![k; 32],
+            power,
+        };
+
+        // A ballot of an election set earlier in the block
+        let txs = vec![
+            signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?,
+            ballot_tx(&ballot)?,
+        ];
+        assert_eq!(process(txs), accept);
+
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![
+                signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?,
+                signed_tx(TEST_ELECTION_SEED, 2, TypeOneof::AddValidator(validator(1, 10)))?,
+                signed_tx(TEST_ELECTION_SEED, 3, TypeOneof::AddValidator(validator(2, 10)))?,
+            ],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+
+        // The lock caps the validator set for the txs after it
+        let txs = vec![
+            signed_tx(TEST_ELECTION_SEED, 4, TypeOneof::Lock(ElectionId::default()))?,
+            signed_tx(TEST_ELECTION_SEED, 5, TypeOneof::AddValidator(validator(3, 10)))?,
+        ];
+        assert_eq!(process(txs), reject);
+
+        // A validator removed and added back
+        let txs = vec![
+            signed_tx(TEST_ELECTION_SEED, 4, TypeOneof::RemoveValidator(validator(2, 0)))?,
+            signed_tx(TEST_ELECTION_SEED, 5, TypeOneof::AddValidator(validator(2, 5)))?,
+        ];
+        assert_eq!(process(txs), accept);
+
+        // Signed by the new authority after a rotation
+        let rotate = TypeOneof::RotateAuthority(Authority {
+            pub_key: authority_key_from_seed(&Network::MainNetwork, TEST_SEED)?.to_vec(),
+        });
+        let txs = vec![
+            signed_tx(TEST_ELECTION_SEED, 4, rotate)?,
+            signed_tx(TEST_SEED, 5, TypeOneof::Lock(ElectionId::default()))?,
+        ];
+        assert_eq!(process(txs), accept);
+
+        // Nothing was applied
+        rt.block_on(async {
+            let state = app.state.lock().await;
+            assert!(!state.any_locked());
+            assert_eq!(state.validators.get(&vec![2u8; 32]), Some(&10));
+            assert_eq!(state.admin_nonce, 3);
+        });
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_app_hash_covers_admin_state() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+        let app = rt.block_on(test_server(pool))?;
+        let finalize = |height: i64, txs: Vec<Bytes>| {
+            let rep = app.finalize_block(RequestFinalizeBlock {
+                txs,
+                height,
+                ..RequestFinalizeBlock::default()
+            });
+            app.commit();
+            rep.app_hash
+        };
+        let empty = finalize(1, vec![]);
+        // No ballot and no election, only the validators and the nonce change
+        let validator = Validator {
+            pub_key: vec![1u8; 32],
+            power: 10,
+        };
+        let added = finalize(
+            2,
+            vec![signed_tx(TEST_ELECTION_SEED, 1, TypeOneof::AddValidator(validator))?],
+        );
+        assert_ne!(added, empty);
+        let rotate = TypeOneof::RotateAuthority(Authority {
+            pub_key: authority_key_from_seed(&Network::MainNetwork, TEST_SEED)?.to_vec(),
+        });
+        let rotated = finalize(3, vec![signed_tx(TEST_ELECTION_SEED, 2, rotate)?]);
+        assert_ne!(rotated, added);
+        assert_eq!(finalize(4, vec![]), rotated);
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_query() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+        let app = rt.block_on(test_server(pool))?;
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let query = |path: &str, height: i64| {
+            app.query(RequestQuery {
+                path: path.to_string(),
+                height,
+                ..RequestQuery::default()
+            })
+        };
+
+        let (e, _) = test_election()?;
+        let rep = query("/election", 0);
+        assert_eq!(rep.code, QUERY_OK);
+        assert_eq!(rep.height, 1);
+        let value: serde_json::Value = serde_json::from_slice(&rep.value)?;
+        assert_eq!(value["election"]["name"], e.name);
+
+        let rep = query(&format!("/election/{}", hex::encode(&e.domain)), 0);
+        assert_eq!(rep.code, QUERY_OK);
+        let rep = query(&format!("/election/{}", hex::encode([0u8; 32])), 0);
+        assert_eq!(rep.code, QUERY_NOT_FOUND);
+
+        let cmx_root = rt.block_on(async {
+            let state = app.state.lock().await;
+            state.elections[&tiu!(e.domain.clone())]
+                .cmx_tree
+                .root(&OrchardHasher::default())
+        });
+        let rep = query(&format!("/cmx_root/{}", hex::encode(cmx_root)), 1);
+        assert_eq!(rep.code, QUERY_OK);
+        let value: serde_json::Value = serde_json::from_slice(&rep.value)?;
+        assert_eq!(value["height"], e.end);
+        assert_eq!(value["domain"], hex::encode(&e.domain));
+
+        let rep = query(&format!("/nullifier/{}", hex::encode([0u8; 32])), 0);
+        assert_eq!(rep.code, QUERY_NOT_FOUND);
+
+        let rep = query("/state", 0);
+        assert_eq!(rep.code, QUERY_OK);
+        let value: serde_json::Value = serde_json::from_slice(&rep.value)?;
+        let info = app.info(RequestInfo::default());
+        assert_eq!(value["app_hash"], hex::encode(&info.last_block_app_hash));
+        assert_eq!(value["elections"][0]["cmx_root"], hex::encode(cmx_root));
+        assert_eq!(value["admin_nonce"], 1);
+
+        assert_eq!(query("/election", 2).code, QUERY_ERROR);
+        assert_eq!(query("/unknown", 0).code, QUERY_ERROR);
+
+        // The elections are not kept by height
+        app.finalize_block(RequestFinalizeBlock {
+            height: 2,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        assert_eq!(query("/election", 1).code, QUERY_ERROR);
+        assert_eq!(query("/elections", 1).code, QUERY_ERROR);
+        assert_eq!(query("/election", 2).code, QUERY_OK);
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_query_proofs() -> Result<()> {
+        let (rt, pool, [ballot]) = test_fixture()?;
+
+        let app = rt.block_on(test_server(pool))?;
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let res = app.finalize_block(RequestFinalizeBlock {
+            txs: vec![ballot_tx(&ballot)?],
+            height: 2,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        // The app hash of block 2 is in the header of block 3
+        let header_app_hash = res.app_hash.to_vec();
+        let query = |path: String, height: i64| {
+            app.query(RequestQuery {
+                path,
+                height,
+                prove: true,
+                ..RequestQuery::default()
+            })
+        };
+        let verify = |leaf: [u8; 32], rep: &ResponseQuery| -> Result<Vec<u8>> {
+            let proof_ops = rep.proof_ops.as_ref().ok_or(anyhow::anyhow!("No proof"))?;
+            Ok(verify_proof_ops(leaf, proof_ops)?.to_vec())
+        };
+
+        let dnf = ballot.data.actions[0].nf;
+        let sighash = ballot.data.sighash()?;
+        let rep = query(format!("/nullifier/{}", hex::encode(dnf)), 0);
+        assert_eq!(rep.code, QUERY_OK);
+        let value: serde_json::Value = serde_json::from_slice(&rep.value)?;
+        let spent_by = hex::decode(value["ballot"].as_str().unwrap())?;
+        assert_eq!(verify(nullifier_leaf(&dnf, &spent_by), &rep)?, header_app_hash);
+        assert_ne!(verify(nullifier_leaf(&dnf, &[0u8; 32]), &rep)?, header_app_hash);
+
+        let rep = query(format!("/ballot/{}", hex::encode(sighash)), 0);
+        assert_eq!(rep.code, QUERY_OK);
+        assert_eq!(verify(nullifier_leaf(&dnf, &sighash), &rep)?, header_app_hash);
+
+        let rep = query("/election".to_string(), 0);
+        let value: serde_json::Value = serde_json::from_slice(&rep.value)?;
+        let field = |name: &str| hex::decode(value[name].as_str().unwrap());
+        let leaf = election_leaf(
+            &field("domain")?,
+            &field("nf_root")?,
+            &field("cmx_root")?,
+            value["locked"].as_bool().unwrap(),
+        );
+        assert_eq!(verify(leaf, &rep)?, header_app_hash);
+
+        // Only the latest state is kept
+        let rep = query(format!("/nullifier/{}", hex::encode(dnf)), 1);
+        assert_eq!(rep.code, QUERY_ERROR);
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_multiple_elections() -> Result<()> {
+        let (rt, pool, [ballot]) = test_fixture()?;
+
+        let (e, cmx_tree_state) = test_election()?;
+        let mut other: ElectionProps = serde_json::from_value(TEST_ELECTION.clone())?;
+        other.name = "Other Election".to_string();
+        let other = other.build(TEST_ELECTION_SEED)?;
+        assert_ne!(other.domain, e.domain);
+        let set_other = TypeOneof::SetElection(Election {
+            election: serde_json::to_string(&other)?,
+            nf_root: vec![0u8; 32],
+            cmx_tree_state,
+        });
+        let app = rt.block_on(test_server(pool.clone()))?;
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![
+                signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?,
+                signed_tx(TEST_ELECTION_SEED, 2, set_other)?,
+            ],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let cmx_roots = |app: &Server| {
+            rt.block_on(async {
+                let state = app.state.lock().await;
+                state
+                    .elections
+                    .values()
+                    .map(|e| e.cmx_tree.root(&OrchardHasher::default()))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let before = cmx_roots(&app);
+        assert_eq!(before.len(), 2);
+
+        // Only the cmx tree of the election of the ballot grows
+        let res = app.finalize_block(RequestFinalizeBlock {
+            txs: vec![ballot_tx(&ballot)?],
+            height: 2,
+            ..RequestFinalizeBlock::default()
+        });
+        assert_eq!(res.tx_results[0].code, 0);
+        app.commit();
+        let after = cmx_roots(&app);
+        let changed: Vec<_> = before.iter().zip(&after).map(|(b, a)| b != a).collect();
+        assert_eq!(changed.iter().filter(|c| **c).count(), 1);
+
+        // An existing election cannot be set again
+        let res = app.check_tx(RequestCheckTx {
+            tx: signed_tx(TEST_ELECTION_SEED, 3, set_election()?)?,
+            r#type: CheckTxType::New as i32,
+        });
+        assert!(res.log.contains("Election already exists"));
+
+        let mut stray = ballot.clone();
+        stray.data.domain = [0u8; 32];
+        let res = app.check_tx(RequestCheckTx {
+            tx: ballot_tx(&stray)?,
+            r#type: CheckTxType::New as i32,
+        });
+        assert!(res.log.contains("Unknown election domain"));
+
+        let rep = app.query(RequestQuery {
+            path: "/elections".to_string(),
+            ..RequestQuery::default()
+        });
+        let value: serde_json::Value = serde_json::from_slice(&rep.value)?;
+        assert_eq!(value.as_array().map(|a| a.len()), Some(2));
+        // The election must be named
+        let rep = app.query(RequestQuery {
+            path: "/election".to_string(),
+            ..RequestQuery::default()
+        });
+        assert_eq!(rep.code, QUERY_ERROR);
+
+        // Elections are locked one at a time
+        let res = app.check_tx(RequestCheckTx {
+            tx: signed_tx(TEST_ELECTION_SEED, 3, TypeOneof::Lock(ElectionId::default()))?,
+            r#type: CheckTxType::New as i32,
+        });
+        assert!(res.log.contains("Election domain required"));
+        let lock_other = TypeOneof::Lock(ElectionId {
+            domain: other.domain.clone(),
+        });
+        let res = app.finalize_block(RequestFinalizeBlock {
+            txs: vec![signed_tx(TEST_ELECTION_SEED, 3, lock_other)?],
+            height: 3,
+            ..RequestFinalizeBlock::default()
+        });
+        assert_eq!(res.tx_results[0].code, 0);
+        app.commit();
+        drop(app);
+
+        // Both elections are restored
+        let app = rt.block_on(Server::new(pool, "", true))?;
+        assert_eq!(cmx_roots(&app), after);
+        rt.block_on(async {
+            let state = app.state.lock().await;
+            let locked = |domain: &[u8]| state.elections[domain].locked;
+            assert!(!locked(&e.domain));
+            assert!(locked(&other.domain));
+        });
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_snapshot_restore() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+        let app = rt.block_on(test_server(pool))?;
+        rt.block_on(async {
+            app.state.lock().await.snapshot_interval = 1;
+        });
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![
+                signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?,
+                signed_tx(TEST_ELECTION_SEED, 2, TypeOneof::Lock(ElectionId::default()))?,
+            ],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let info = app.info(RequestInfo::default());
+        // The snapshot is taken in the background after the commit
+        let mut snapshots = vec![];
+        for _ in 0..50 {
+            snapshots = app.list_snapshots().snapshots;
+            if !snapshots.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        assert_eq!(snapshots.len(), 1);
+        let snapshot = snapshots[0].clone();
+        assert_eq!(snapshot.height, 1);
+        let chunks: Vec<_> = (0..snapshot.chunks)
+            .map(|chunk| {
+                app.load_snapshot_chunk(RequestLoadSnapshotChunk {
+                    height: snapshot.height,
+                    format: snapshot.format,
+                    chunk,
+                })
+                .chunk
+            })
+            .collect();
+
+        let restore = |app_hash: &[u8]| -> Result<(Server, i32)> {
+            let pool = rt.block_on(test_pool_at("server-test-2.db"))?;
+            let app2 = rt.block_on(test_server(pool))?;
+            // Tells the sender of the vote subscriptions apart from a new one
+            rt.block_on(async {
+                app2.state.lock().await.block_notify.send_replace(7);
+            });
+            let rep = app2.offer_snapshot(RequestOfferSnapshot {
+                snapshot: Some(snapshot.clone()),
+                app_hash: app_hash.to_vec().into(),
+            });
+            assert_eq!(rep.result, response_offer_snapshot::Result::Accept as i32);
+            let mut result = 0;
+            for (index, chunk) in chunks.iter().enumerate() {
+                result = app2
+                    .apply_snapshot_chunk(RequestApplySnapshotChunk {
+                        index: index as u32,
+                        chunk: chunk.clone(),
+                        sender: "peer".to_string(),
+                    })
+                    .result;
+            }
+            Ok((app2, result))
+        };
+
+        // Does not match the trusted app hash
+        let (app2, result) = restore(&[0u8; 32])?;
+        assert_eq!(
+            result,
+            response_apply_snapshot_chunk::Result::RejectSnapshot as i32
+        );
+        assert_eq!(app2.info(RequestInfo::default()).last_block_height, 0);
+        // The reset keeps the configured authority and the vote subscriptions
+        rt.block_on(async {
+            let state = app2.state.lock().await;
+            assert_eq!(
+                state.authority,
+                Some(authority_key_from_seed(&Network::MainNetwork, TEST_ELECTION_SEED)?)
+            );
+            assert_eq!(*state.block_notify.borrow(), 7);
+            Ok::<_, anyhow::Error>(())
+        })?;
+
+        let (app2, result) = restore(&info.last_block_app_hash)?;
+        assert_eq!(result, response_apply_snapshot_chunk::Result::Accept as i32);
+        let info2 = app2.info(RequestInfo::default());
+        assert_eq!(info2.last_block_height, 1);
+        assert_eq!(info2.last_block_app_hash, info.last_block_app_hash);
+        rt.block_on(async {
+            let state = app2.state.lock().await;
+            assert_eq!(state.elections.len(), 1);
+            assert!(state.any_locked());
+            assert_eq!(state.admin_nonce, 2);
+        });
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_recheck_double_spend() -> Result<()> {
+        let (rt, pool, [a, mut b, c]) = test_fixture()?;
+        // Competing ballot that spends the same note as a
+        b.data.actions[0] = a.data.actions[0].clone();
+
+        let app = rt.block_on(test_server(pool))?;
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let check = |tx: Bytes, r#type: CheckTxType| {
+            app.check_tx(RequestCheckTx {
+                tx,
+                r#type: r#type as i32,
+            })
+            .code
+        };
+
+        // Both are valid until one of them is included
+        for ballot in [&a, &b, &c] {
+            assert_eq!(check(ballot_tx(ballot)?, CheckTxType::New), 0);
+        }
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![ballot_tx(&a)?],
+            height: 2,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+
+        assert_ne!(check(ballot_tx(&b)?, CheckTxType::Recheck), 0);
+        assert_ne!(check(ballot_tx(&b)?, CheckTxType::New), 0);
+        assert_eq!(check(ballot_tx(&c)?, CheckTxType::Recheck), 0);
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_rejected_ballot_keeps_cmx_tree() -> Result<()> {
+        let (rt, pool, [a, mut b]) = test_fixture()?;
+        b.data.actions[0] = a.data.actions[0].clone();
+
+        let app = rt.block_on(test_server(pool))?;
+        let finalize = |height: i64, txs: Vec<Bytes>| {
+            let res = app.finalize_block(RequestFinalizeBlock {
+                txs,
+                height,
+                ..RequestFinalizeBlock::default()
+            });
+            app.commit();
+            res
+        };
+        let cmx_root = || {
+            rt.block_on(async {
+                let state = app.state.lock().await;
+                let e = state.elections.values().next().unwrap();
+                e.cmx_tree.root(&OrchardHasher::default())
+            })
+        };
+        finalize(1, vec![signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?]);
+        let res = finalize(2, vec![ballot_tx(&a)?]);
+        let root = cmx_root();
+
+        // b spends the nullifier of a and is not stored. Its cmxs
+        // must not reach the tree
+        let res2 = finalize(3, vec![ballot_tx(&b)?]);
+        assert_eq!(cmx_root(), root);
+        assert_eq!(res2.app_hash, res.app_hash);
+        Ok(())
+    }
+
+    // With the proofs verified: a ballot verified before the block is a
+    // cache hit, and one that is not is verified again and rejected, as
+    // the minted ballots have no proofs
+    #[test]
+    #[serial_test::serial]
+    fn test_block_verification() -> Result<()> {
+        let (rt, pool, [a, b]) = test_fixture()?;
+
+        let app = rt.block_on(test_server(pool))?;
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let cache = rt.block_on(async {
+            let mut state = app.state.lock().await;
+            state.skip_validation = false;
+            // The anchors of the minted ballots
+            let domain = *state.elections.keys().next().unwrap();
+            store_cmx_root(&mut *state.pool.acquire().await?, &domain, &[0u8; 32], 0).await?;
+            Ok::<_, anyhow::Error>(state.check_witnesses_cache.clone())
+        })?;
+        let (key_a, key_b) = (VerificationCache::key(&a)?, VerificationCache::key(&b)?);
+        // As if a had been verified by check_tx
+        cache.lock().insert(key_a);
+
+        // b is verified in parallel with the other ballots of its
+        // election and is left out of the cache
+        rt.block_on(async {
+            let state = app.state.lock().await;
+            let e = state.elections.values().next().unwrap();
+            let mut conn = state.pool.acquire().await?;
+            ServerState::check_witnesses_batch(
+                &mut conn,
+                &e.election,
+                vec![a.clone(), b.clone()],
+                e.domain,
+                e.nf_root,
+                cache.clone(),
+                false,
+            )
+            .await?;
+            Ok::<_, anyhow::Error>(())
+        })?;
+        assert!(cache.lock().contains(&key_a));
+        assert!(!cache.lock().contains(&key_b));
+
+        let res = app.finalize_block(RequestFinalizeBlock {
+            txs: vec![ballot_tx(&a)?, ballot_tx(&b)?],
+            height: 2,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        assert_eq!(res.tx_results[0].code, 0);
+        assert_ne!(res.tx_results[1].code, 0);
+        assert!(!cache.lock().contains(&key_b));
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_voting_deadline() -> Result<()> {
+        let (rt, pool, [a, b]) = test_fixture()?;
+
+        let (mut e, cmx_tree_state) = test_election()?;
+        e.close_height = e.end + 2;
+        let set_election = TypeOneof::SetElection(Election {
+            election: serde_json::to_string(&e)?,
+            nf_root: vec![0u8; 32],
+            cmx_tree_state,
+        });
+        let app = rt.block_on(test_server(pool))?;
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![signed_tx(TEST_ELECTION_SEED, 1, set_election)?],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+
+        // Block 2 is the last one that accepts ballots
+        let res = app.check_tx(RequestCheckTx {
+            tx: ballot_tx(&a)?,
+            r#type: CheckTxType::New as i32,
+        });
+        assert_eq!(res.code, 0);
+        let res = app.finalize_block(RequestFinalizeBlock {
+            txs: vec![ballot_tx(&a)?],
+            height: 2,
+            ..RequestFinalizeBlock::default()
+        });
+        assert_eq!(res.tx_results[0].code, 0);
+        app.commit();
+
+        let res = app.check_tx(RequestCheckTx {
+            tx: ballot_tx(&b)?,
+            r#type: CheckTxType::New as i32,
+        });
+        assert_ne!(res.code, 0);
+        let res = app.process_proposal(RequestProcessProposal {
+            txs: vec![ballot_tx(&b)?],
+            height: 3,
+            ..RequestProcessProposal::default()
+        });
+        assert_eq!(res.status, ProposalStatus::Reject as i32);
+        let res = app.finalize_block(RequestFinalizeBlock {
+            txs: vec![ballot_tx(&b)?],
+            height: 3,
+            ..RequestFinalizeBlock::default()
+        });
+        assert_ne!(res.tx_results[0].code, 0);
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_finalize_events() -> Result<()> {
+        let (rt, pool, [ballot]) = test_fixture()?;
+
+        let app = rt.block_on(test_server(pool))?;
+        let res = app.finalize_block(RequestFinalizeBlock {
+            txs: vec![
+                signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?,
+                signed_tx(TEST_ELECTION_SEED, 2, TypeOneof::Lock(ElectionId::default()))?,
+                ballot_tx(&ballot)?,
+            ],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let kinds: Vec<_> = res
+            .tx_results
+            .iter()
+            .map(|r| r.events[0].r#type.as_str())
+            .collect();
+        assert_eq!(kinds, [EVENT_ELECTION, EVENT_LOCK, EVENT_BALLOT]);
+
+        let event = &res.tx_results[2].events[0];
+        let values = |key: &str| -> Vec<String> {
+            event
+                .attributes
+                .iter()
+                .filter(|a| a.key == key)
+                .map(|a| a.value.clone())
+                .collect()
+        };
+        assert_eq!(values("sighash"), [hex::encode(ballot.data.sighash()?)]);
+        assert_eq!(values("nf").len(), ballot.data.actions.len());
+        assert_eq!(values("cmx").len(), ballot.data.actions.len());
+        let (e, _) = test_election()?;
+        assert_eq!(values("height"), [(e.end + 1).to_string()]);
+        let cmx_root = rt.block_on(async {
+            let state = app.state.lock().await;
+            state.elections[&ballot.data.domain]
+                .cmx_tree
+                .root(&OrchardHasher::default())
+        });
+        assert_eq!(values("cmx_root"), [hex::encode(cmx_root)]);
+        assert!(event.attributes.iter().all(|a| a.index));
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_wait_for_commit() -> Result<()> {
+        let (rt, pool, []) = test_fixture()?;
+        let app = rt.block_on(Server::new(pool, "", true))?;
+        app.finalize_block(RequestFinalizeBlock {
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        let wait = |timeout: std::time::Duration| {
+            rt.block_on(async { tokio::time::timeout(timeout, app.wait_for_commit()).await.is_ok() })
+        };
+        // Between finalize_block and commit
+        assert!(!wait(std::time::Duration::from_millis(300)));
+        app.commit();
+        assert!(wait(std::time::Duration::from_secs(5)));
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_ballot_admission() -> Result<()> {
+        let (rt, pool, [ballot]) = test_fixture()?;
+
+        let (mut e, cmx_tree_state) = test_election()?;
+        e.pow_bits = 8;
+        let set_election = TypeOneof::SetElection(Election {
+            election: serde_json::to_string(&e)?,
+            nf_root: vec![0u8; 32],
+            cmx_tree_state,
+        });
+        let app = rt.block_on(test_server(pool))?;
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![signed_tx(TEST_ELECTION_SEED, 1, set_election)?],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+
+        let mut bytes = vec![];
+        ballot.write(&mut bytes)?;
+        let sighash = ballot.data.sighash()?;
+        let pow_nonce = mint_pow_stamp(&sighash, 8);
+        let bad_nonce = (0u64..).find(|n| !check_pow_stamp(&sighash, *n, 8)).unwrap();
+        let check = |pow_nonce: u64| {
+            let tx = to_tx(TypeOneof::Ballot(Ballot {
+                ballot: bytes.clone(),
+                pow_nonce,
+                ..Ballot::default()
+            }));
+            app.check_tx(RequestCheckTx {
+                tx,
+                r#type: CheckTxType::New as i32,
+            })
+        };
+        let res = check(bad_nonce);
+        assert_ne!(res.code, 0);
+        assert!(res.log.contains("proof of work"));
+        assert_eq!(check(pow_nonce).code, 0);
+
+        let set_admission = |admission: AdmissionPolicy| {
+            rt.block_on(async {
+                app.state.lock().await.admission = admission;
+            })
+        };
+        set_admission(AdmissionPolicy {
+            max_actions: ballot.data.actions.len() - 1,
+            ..AdmissionPolicy::default()
+        });
+        assert!(check(pow_nonce).log.contains("Too many actions"));
+        set_admission(AdmissionPolicy {
+            max_ballot_size: bytes.len() - 1,
+            ..AdmissionPolicy::default()
+        });
+        assert!(check(pow_nonce).log.contains("Ballot too large"));
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_ballot_tracker() -> Result<()> {
+        let (rt, pool, [a, mut b]) = test_fixture()?;
+        b.data.actions[0] = a.data.actions[0].clone();
+
+        let app = rt.block_on(test_server(pool))?;
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![signed_tx(TEST_ELECTION_SEED, 1, set_election()?)?],
+            height: 1,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        let tracked = |ballot: &orchard_vote::Ballot| -> Result<Option<TrackedBallot>> {
+            let sighash = ballot.data.sighash()?;
+            Ok(rt.block_on(async {
+                let state = app.state.lock().await;
+                state.ballot_tracker.lock().get(&sighash)
+            }))
+        };
+
+        for ballot in [&a, &b] {
+            app.check_tx(RequestCheckTx {
+                tx: ballot_tx(ballot)?,
+                r#type: CheckTxType::New as i32,
+            });
+            assert_eq!(tracked(ballot)?, Some(TrackedBallot::Pending));
+        }
+        app.finalize_block(RequestFinalizeBlock {
+            txs: vec![ballot_tx(&a)?],
+            height: 2,
+            ..RequestFinalizeBlock::default()
+        });
+        app.commit();
+        // Included ballots are found in the database
+        assert_eq!(tracked(&a)?, None);
+
+        // b is dropped from the mempool
+        app.check_tx(RequestCheckTx {
+            tx: ballot_tx(&b)?,
+            r#type: CheckTxType::Recheck as i32,
+        });
+        assert!(matches!(tracked(&b)?, Some(TrackedBallot::Rejected { height: 0, .. })));
+        Ok(())
+    }
+}
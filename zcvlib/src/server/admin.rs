@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use tonic::{Request, Response, Status, async_trait};
+
+use crate::{
+    context::BFTContext,
+    server::rpc::{submit_message, to_tonic},
+    vote_rpc::{
+        Hash, SignedMessage, VoteMessage, admin_service_server::AdminService,
+        vote_message::TypeOneof,
+    },
+};
+
+// Admin endpoint. It relays the admin messages signed by the operator,
+// the node cannot sign them. Must not be exposed without an admin token
+pub struct ZCVAdminServer {
+    pub context: Arc<Mutex<BFTContext>>,
+}
+
+impl ZCVAdminServer {
+    pub fn new(context: Arc<Mutex<BFTContext>>) -> Self {
+        Self { context }
+    }
+}
+
+#[async_trait]
+impl AdminService for ZCVAdminServer {
+    async fn submit(&self, request: Request<SignedMessage>) -> Result<Response<Hash>, Status> {
+        let m = VoteMessage {
+            type_oneof: Some(TypeOneof::Signed(request.into_inner())),
+        };
+        let rep = submit_message(&self.context, m).await?;
+        let hash = rep
+            .pointer("/result")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let hash = hex::decode(hash).map_err(|e| to_tonic(e.into()))?;
+        Ok(Response::new(Hash { hash }))
+    }
+}
+
+// Interceptor of the admin service. Requests must have the header
+// "authorization: Bearer <token>". No token lets every request through
+pub fn admin_auth(
+    token: Option<String>,
+) -> impl FnMut(Request<()>) -> Result<Request<()>, Status> + Clone {
+    move |request: Request<()>| {
+        let Some(token) = &token else {
+            return Ok(request);
+        };
+        let authorized = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|t| token_eq(t.as_bytes(), token.as_bytes()));
+        if !authorized {
+            return Err(Status::unauthenticated("Invalid admin token"));
+        }
+        Ok(request)
+    }
+}
+
+// Constant time comparison, only the length leaks
+fn token_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::{Code, Request};
+
+    use crate::server::admin::admin_auth;
+
+    #[test]
+    fn test_admin_auth() {
+        let mut auth = admin_auth(Some("secret".to_string()));
+        let request = |header: Option<&str>| {
+            let mut request = Request::new(());
+            if let Some(header) = header {
+                request
+                    .metadata_mut()
+                    .insert("authorization", header.parse().unwrap());
+            }
+            request
+        };
+        assert!(auth(request(Some("Bearer secret"))).is_ok());
+        for header in [None, Some("Bearer secreT"), Some("Bearer secret2"), Some("secret")] {
+            let status = auth(request(header)).unwrap_err();
+            assert_eq!(status.code(), Code::Unauthenticated);
+        }
+
+        let mut open = admin_auth(None);
+        assert!(open(request(None)).is_ok());
+    }
+}
@@ -0,0 +1,143 @@
+use std::{net::IpAddr, num::NonZeroUsize, time::Instant};
+
+use anyhow::anyhow;
+use lru::LruCache;
+
+use crate::{
+    ZCVResult, context::AdmissionPolicy, error::IntoAnyhow, pod::ElectionPropsPub,
+    pow::check_pow_stamp, server::from_protobuf, vote_rpc::Ballot,
+};
+
+// Beyond this number of clients, the least recently seen are forgotten
+const MAX_RATE_CLIENTS: usize = 10_000;
+
+impl AdmissionPolicy {
+    // Checks that are cheap compared to the proof verification
+    // Returns the decoded ballot
+    pub fn admit(
+        &self,
+        ballot: &Ballot,
+        election: &ElectionPropsPub,
+    ) -> ZCVResult<orchard_vote::Ballot> {
+        if ballot.ballot.len() > self.max_ballot_size {
+            return Err(anyhow!(
+                "Ballot too large: {} > {} bytes",
+                ballot.ballot.len(),
+                self.max_ballot_size
+            )
+            .into());
+        }
+        let pow_nonce = ballot.pow_nonce;
+        let ballot = from_protobuf(ballot).anyhow()?;
+        if ballot.data.actions.len() > self.max_actions {
+            return Err(anyhow!(
+                "Too many actions: {} > {}",
+                ballot.data.actions.len(),
+                self.max_actions
+            )
+            .into());
+        }
+        if !check_pow_stamp(&ballot.data.sighash()?, pow_nonce, election.pow_bits) {
+            return Err(anyhow!("Invalid proof of work stamp").into());
+        }
+        Ok(ballot)
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+// Token bucket per client IP: `rate` submissions per minute
+// with bursts of up to `rate`. The clients without an IP share
+// the bucket of None
+pub struct RateLimiter {
+    rate: u32,
+    buckets: LruCache<Option<IpAddr>, Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: u32) -> Self {
+        Self::with_capacity(rate, MAX_RATE_CLIENTS)
+    }
+
+    fn with_capacity(rate: u32, capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            rate,
+            buckets: LruCache::new(capacity),
+        }
+    }
+
+    pub fn check(&mut self, ip: Option<IpAddr>, now: Instant) -> bool {
+        if self.rate == 0 {
+            return true;
+        }
+        let capacity = self.rate as f64;
+        let refill = capacity / 60.0;
+        let bucket = self.buckets.get_or_insert_mut(ip, || Bucket {
+            tokens: capacity,
+            last: now,
+        });
+        bucket.tokens = (bucket.tokens
+            + now.saturating_duration_since(bucket.last).as_secs_f64() * refill)
+            .min(capacity);
+        bucket.last = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    use crate::server::admission::RateLimiter;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(2);
+        let a = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        let b = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+        let now = Instant::now();
+        assert!(limiter.check(a, now));
+        assert!(limiter.check(a, now));
+        assert!(!limiter.check(a, now));
+        // Other clients are not affected
+        assert!(limiter.check(b, now));
+        // One token every 30 s
+        assert!(limiter.check(a, now + Duration::from_secs(30)));
+        assert!(!limiter.check(a, now + Duration::from_secs(31)));
+
+        // Clients without an IP share a bucket
+        assert!(limiter.check(None, now));
+        assert!(limiter.check(None, now));
+        assert!(!limiter.check(None, now));
+
+        let mut unlimited = RateLimiter::new(0);
+        assert!((0..100).all(|_| unlimited.check(a, now)));
+    }
+
+    #[test]
+    fn test_rate_limiter_capacity() {
+        let mut limiter = RateLimiter::with_capacity(1, 2);
+        let ip = |i: u8| Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, i)));
+        let now = Instant::now();
+        assert!(limiter.check(ip(1), now));
+        assert!(limiter.check(ip(2), now));
+        // 1 is seen again, 2 is the least recently seen
+        assert!(!limiter.check(ip(1), now));
+        assert!(limiter.check(ip(3), now));
+        assert_eq!(limiter.buckets.len(), 2);
+        // 2 was evicted and starts with a full bucket, 3 is still limited
+        assert!(limiter.check(ip(2), now));
+        assert!(!limiter.check(ip(3), now));
+    }
+}
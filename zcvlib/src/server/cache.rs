@@ -0,0 +1,84 @@
+use std::num::NonZeroUsize;
+
+use blake2b_simd::Params;
+use lru::LruCache;
+
+use crate::{ZCVResult, tiu};
+
+pub const VERIFICATION_CACHE_SIZE: usize = 100_000;
+
+// Ballots that passed the anchor and proof checks.
+// The key covers the anchors so that an entry stays valid as long as
+// the election does not change: cmx roots are only ever added
+pub struct VerificationCache {
+    verified: LruCache<[u8; 32], ()>,
+}
+
+impl VerificationCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            verified: LruCache::new(capacity),
+        }
+    }
+
+    pub fn key(ballot: &orchard_vote::Ballot) -> ZCVResult<[u8; 32]> {
+        let sighash = ballot.data.sighash()?;
+        let hash = Params::new()
+            .personal(b"ZCVote_VerifyKey")
+            .hash_length(32)
+            .to_state()
+            .update(&sighash)
+            .update(&ballot.data.anchors.nf)
+            .update(&ballot.data.anchors.cmx)
+            .finalize();
+        Ok(tiu!(hash.as_bytes()))
+    }
+
+    pub fn contains(&mut self, key: &[u8; 32]) -> bool {
+        self.verified.get(key).is_some()
+    }
+
+    pub fn insert(&mut self, key: [u8; 32]) {
+        self.verified.put(key, ());
+    }
+
+    pub fn len(&self) -> usize {
+        self.verified.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verified.is_empty()
+    }
+
+    // When the election and its roots are replaced
+    pub fn clear(&mut self) {
+        self.verified.clear();
+    }
+}
+
+impl Default for VerificationCache {
+    fn default() -> Self {
+        Self::new(VERIFICATION_CACHE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::cache::VerificationCache;
+
+    #[test]
+    fn test_verification_cache_bound() {
+        let mut cache = VerificationCache::new(2);
+        cache.insert([1u8; 32]);
+        cache.insert([2u8; 32]);
+        assert!(cache.contains(&[1u8; 32]));
+        // 2 is the least recently used
+        cache.insert([3u8; 32]);
+        assert!(!cache.contains(&[2u8; 32]));
+        assert!(cache.contains(&[1u8; 32]));
+        assert_eq!(cache.len(), 2);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
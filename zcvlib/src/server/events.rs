@@ -0,0 +1,83 @@
+use tendermint_proto::abci::{Event, EventAttribute};
+
+use crate::pod::ElectionPropsPub;
+
+// ABCI events of the vote chain txs
+// Indexed attributes can be searched with tx_search,
+// for example "ballot.nf='<dnf>'"
+pub const EVENT_BALLOT: &str = "ballot";
+pub const EVENT_ELECTION: &str = "set_election";
+pub const EVENT_LOCK: &str = "lock";
+pub const EVENT_VALIDATOR: &str = "validator";
+pub const EVENT_AUTHORITY: &str = "rotate_authority";
+
+fn attribute(key: &str, value: String, index: bool) -> EventAttribute {
+    EventAttribute {
+        key: key.to_string(),
+        value,
+        index,
+    }
+}
+
+fn event(kind: &str, attributes: Vec<EventAttribute>) -> Event {
+    Event {
+        r#type: kind.to_string(),
+        attributes,
+    }
+}
+
+// Every domain nullifier and output cmx of the ballot,
+// and the cmx root after it was appended
+pub fn ballot_event(
+    sighash: &[u8],
+    dnfs: &[Vec<u8>],
+    cmxs: &[Vec<u8>],
+    vote_height: u32,
+    cmx_root: &[u8],
+) -> Event {
+    let mut attributes = vec![
+        attribute("sighash", hex::encode(sighash), true),
+        attribute("height", vote_height.to_string(), true),
+        attribute("cmx_root", hex::encode(cmx_root), true),
+    ];
+    for dnf in dnfs {
+        attributes.push(attribute("nf", hex::encode(dnf), true));
+    }
+    for cmx in cmxs {
+        attributes.push(attribute("cmx", hex::encode(cmx), true));
+    }
+    event(EVENT_BALLOT, attributes)
+}
+
+pub fn election_event(election: &ElectionPropsPub) -> Event {
+    event(
+        EVENT_ELECTION,
+        vec![
+            attribute("domain", hex::encode(&election.domain), true),
+            attribute("name", election.name.clone(), false),
+            attribute("end", election.end.to_string(), false),
+        ],
+    )
+}
+
+pub fn lock_event(domain: &[u8]) -> Event {
+    event(EVENT_LOCK, vec![attribute("domain", hex::encode(domain), true)])
+}
+
+// power 0 for a removed validator
+pub fn validator_event(pub_key: &[u8], power: u32) -> Event {
+    event(
+        EVENT_VALIDATOR,
+        vec![
+            attribute("pub_key", hex::encode(pub_key), true),
+            attribute("power", power.to_string(), false),
+        ],
+    )
+}
+
+pub fn authority_event(pub_key: &[u8]) -> Event {
+    event(
+        EVENT_AUTHORITY,
+        vec![attribute("pub_key", hex::encode(pub_key), true)],
+    )
+}
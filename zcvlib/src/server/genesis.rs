@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::pod::ElectionPropsPub;
+
+// Initial state of the vote chain, read from the `app_state`
+// of the CometBFT genesis file
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize, Default, Debug)]
+pub struct GenesisState {
+    pub election: Option<ElectionPropsPub>,
+    #[serde_as(as = "serde_with::hex::Hex")]
+    #[serde(default)]
+    pub nf_root: Vec<u8>,
+    #[serde_as(as = "serde_with::hex::Hex")]
+    #[serde(default)]
+    pub cmx_tree_state: Vec<u8>,
+    #[serde(default)]
+    pub validators: Vec<GenesisValidator>,
+    #[serde(default)]
+    pub locked: bool,
+    // Defaults to the authority of the election
+    #[serde_as(as = "serde_with::hex::Hex")]
+    #[serde(default)]
+    pub authority: Vec<u8>,
+}
+
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct GenesisValidator {
+    // ed25519 public key
+    #[serde_as(as = "serde_with::hex::Hex")]
+    pub pub_key: Vec<u8>,
+    pub power: u32,
+}
@@ -0,0 +1,55 @@
+use std::{sync::Arc, time::Duration};
+
+use sqlx::{SqlitePool, query_as};
+use tokio::sync::Mutex;
+use tonic::server::NamedService;
+use tonic_health::{ServingStatus, server::HealthReporter};
+
+use crate::{
+    ZCVResult, context::BFTContext, server::rpc::ZCVServer,
+    vote_rpc::vote_streamer_server::VoteStreamerServer,
+};
+
+// CometBFT is considered disconnected if no block is committed
+// for this long
+pub const HEALTH_STALL: Duration = Duration::from_secs(60);
+
+async fn has_election(pool: &SqlitePool) -> ZCVResult<bool> {
+    let (count,): (u32,) = query_as("SELECT COUNT(*) FROM v_elections")
+        .fetch_one(pool)
+        .await?;
+    Ok(count > 0)
+}
+
+// Update the health of the VoteStreamer (and of the server) after every
+// block. It is serving when CometBFT commits blocks and the election is set
+pub async fn report_health(context: Arc<Mutex<BFTContext>>, reporter: HealthReporter) {
+    let (pool, mut notify) = {
+        let c = context.lock().await;
+        (c.context.pool.clone(), c.block_notify.subscribe())
+    };
+    set_status(&reporter, ServingStatus::NotServing).await;
+    loop {
+        let abci = match tokio::time::timeout(HEALTH_STALL, notify.changed()).await {
+            Ok(Ok(())) => true,
+            // The ABCI app has stopped
+            Ok(Err(_)) => break,
+            Err(_) => false,
+        };
+        let election = has_election(&pool).await.unwrap_or_default();
+        let status = if abci && election {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        };
+        set_status(&reporter, status).await;
+    }
+    set_status(&reporter, ServingStatus::NotServing).await;
+}
+
+async fn set_status(reporter: &HealthReporter, status: ServingStatus) {
+    reporter
+        .set_service_status(VoteStreamerServer::<ZCVServer>::NAME, status)
+        .await;
+    reporter.set_service_status("", status).await;
+}
@@ -0,0 +1,168 @@
+use std::{
+    io::{self, BufReader, ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
+
+use prost::Message;
+use tendermint_abci::RequestDispatcher;
+use tendermint_proto::abci::Request;
+
+// ABCI socket server. Unlike tendermint_abci::Server, whose listener
+// never returns, it stops: the listener and the connections of CometBFT
+// are closed so that no new block starts and the thread can be joined
+pub struct AbciListener {
+    addr: SocketAddr,
+    // None once stopped
+    connections: Arc<Mutex<Option<Vec<TcpStream>>>>,
+    thread: JoinHandle<()>,
+}
+
+impl AbciListener {
+    pub fn bind<App>(addr: SocketAddr, app: App) -> io::Result<Self>
+    where
+        App: RequestDispatcher + Clone + Send + 'static,
+    {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let connections = Arc::new(Mutex::new(Some(vec![])));
+        let thread = {
+            let connections = connections.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            tracing::warn!("ABCI accept failed: {e}");
+                            continue;
+                        }
+                    };
+                    {
+                        let mut connections = connections.lock().unwrap();
+                        let Some(connections) = connections.as_mut() else {
+                            break;
+                        };
+                        match stream.try_clone() {
+                            Ok(s) => connections.push(s),
+                            Err(e) => {
+                                tracing::warn!("ABCI connection failed: {e}");
+                                continue;
+                            }
+                        }
+                    }
+                    let app = app.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = serve_connection(stream, app) {
+                            tracing::info!("ABCI connection closed: {e}");
+                        }
+                    });
+                }
+            })
+        };
+        Ok(AbciListener {
+            addr,
+            connections,
+            thread,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // Close the connections, then wake up the listener so that it
+    // sees it is stopped, and wait for it
+    pub fn stop(self) {
+        let connections = self.connections.lock().unwrap().take();
+        for c in connections.into_iter().flatten() {
+            let _ = c.shutdown(Shutdown::Both);
+        }
+        let _ = TcpStream::connect(self.addr);
+        let _ = self.thread.join();
+    }
+}
+
+// Requests and responses are protobuf messages prefixed by their
+// length as a varint
+fn serve_connection<App: RequestDispatcher>(stream: TcpStream, app: App) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    while let Some(len) = read_length(&mut reader)? {
+        let mut buf = vec![0u8; len];
+        reader.read_exact(&mut buf)?;
+        let request = Request::decode(buf.as_slice()).map_err(io::Error::other)?;
+        let response = app.handle(request);
+        writer.write_all(&response.encode_length_delimited_to_vec())?;
+    }
+    Ok(())
+}
+
+// None when CometBFT closed the connection
+fn read_length(reader: &mut impl Read) -> io::Result<Option<usize>> {
+    let mut bytes = vec![];
+    loop {
+        let mut b = [0u8];
+        match reader.read_exact(&mut b) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && bytes.is_empty() => {
+                return Ok(None);
+            }
+            r => r?,
+        }
+        bytes.push(b[0]);
+        if b[0] & 0x80 == 0 {
+            break;
+        }
+        if bytes.len() == 10 {
+            return Err(io::Error::other("Invalid message length"));
+        }
+    }
+    let len = prost::decode_length_delimiter(bytes.as_slice()).map_err(io::Error::other)?;
+    Ok(Some(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpStream},
+    };
+
+    use prost::Message;
+    use tendermint_abci::Application;
+    use tendermint_proto::abci::{Request, RequestEcho, Response, ResponseEcho, request, response};
+
+    use crate::server::listener::{AbciListener, read_length};
+
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Application for Echo {}
+
+    #[test]
+    fn test_abci_listener() -> anyhow::Result<()> {
+        let listener = AbciListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)), Echo)?;
+        let mut stream = TcpStream::connect(listener.local_addr())?;
+        let request = Request {
+            value: Some(request::Value::Echo(RequestEcho {
+                message: "hello".to_string(),
+            })),
+        };
+        stream.write_all(&request.encode_length_delimited_to_vec())?;
+        let len = read_length(&mut stream)?.unwrap();
+        let mut buf = vec![0u8; len];
+        stream.read_exact(&mut buf)?;
+        let response = Response::decode(buf.as_slice())?;
+        assert!(matches!(
+            response.value,
+            Some(response::Value::Echo(ResponseEcho { message })) if message == "hello"
+        ));
+
+        // The connection is closed and the listener thread ends
+        let addr = listener.local_addr();
+        listener.stop();
+        assert_eq!(read_length(&mut stream)?, None);
+        assert!(TcpStream::connect(addr).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use anyhow::anyhow;
+use blake2b_simd::Params;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use tendermint_proto::crypto::{ProofOp, ProofOps};
+
+use crate::{ZCVResult, tiu};
+
+// Binary Merkle trees of the app hash. They split like RFC 6962:
+// the left subtree of a tree of n leaves is the perfect tree
+// of the largest power of two below n
+pub type Hash = [u8; 32];
+
+// Proof of a leaf in the nullifier tree, its key is the nullifier
+pub const PROOF_NULLIFIER: &str = "zcv:nullifier";
+// Proof of a leaf in the state tree, whose root is the app hash
+pub const PROOF_STATE: &str = "zcv:state";
+
+const LEAF_NULLIFIER: u8 = 0;
+const LEAF_ELECTION: u8 = 1;
+const LEAF_VALIDATOR: u8 = 2;
+const LEAF_ADMIN: u8 = 3;
+
+fn leaf_hash(tag: u8, parts: &[&[u8]]) -> Hash {
+    let mut state = Params::new()
+        .personal(b"ZCVote_MerkLeaf_")
+        .hash_length(32)
+        .to_state();
+    state.update(&[tag]);
+    for part in parts {
+        state.update(part);
+    }
+    tiu!(state.finalize().as_bytes())
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let hash = Params::new()
+        .personal(b"ZCVote_MerkNode_")
+        .hash_length(32)
+        .to_state()
+        .update(left)
+        .update(right)
+        .finalize();
+    tiu!(hash.as_bytes())
+}
+
+// A spent domain nullifier and the ballot that spent it
+pub fn nullifier_leaf(dnf: &[u8], sighash: &[u8]) -> Hash {
+    leaf_hash(LEAF_NULLIFIER, &[dnf, sighash])
+}
+
+pub fn election_leaf(domain: &[u8], nf_root: &[u8], cmx_root: &[u8], locked: bool) -> Hash {
+    leaf_hash(LEAF_ELECTION, &[domain, nf_root, cmx_root, &[locked as u8]])
+}
+
+pub fn validator_leaf(pub_key: &[u8], power: u32) -> Hash {
+    leaf_hash(LEAF_VALIDATOR, &[pub_key, &power.to_le_bytes()])
+}
+
+pub fn admin_leaf(chain_id: &str, max_validators: u32, authority: &[u8], admin_nonce: u64) -> Hash {
+    leaf_hash(
+        LEAF_ADMIN,
+        &[
+            &(chain_id.len() as u32).to_le_bytes(),
+            chain_id.as_bytes(),
+            &max_validators.to_le_bytes(),
+            authority,
+            &admin_nonce.to_le_bytes(),
+        ],
+    )
+}
+
+// Size of the left subtree of a tree of n > 1 leaves
+fn split(n: u64) -> u64 {
+    let mut k = 1;
+    while k * 2 < n {
+        k *= 2;
+    }
+    k
+}
+
+pub fn root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => [0u8; 32],
+        1 => leaves[0],
+        n => {
+            let k = split(n as u64) as usize;
+            node_hash(&root(&leaves[..k]), &root(&leaves[k..]))
+        }
+    }
+}
+
+// Siblings of the leaf at index, from the bottom of the tree
+pub fn path(leaves: &[Hash], index: usize) -> Vec<Hash> {
+    let n = leaves.len();
+    if n <= 1 {
+        return vec![];
+    }
+    let k = split(n as u64) as usize;
+    let (mut path, sibling) = if index < k {
+        (path(&leaves[..k], index), root(&leaves[k..]))
+    } else {
+        (path(&leaves[k..], index - k), root(&leaves[..k]))
+    };
+    path.push(sibling);
+    path
+}
+
+// Root of the tree of total leaves that has leaf at index
+pub fn root_from_path(leaf: Hash, index: u64, total: u64, path: &[Hash]) -> Option<Hash> {
+    if index >= total {
+        return None;
+    }
+    if total == 1 {
+        return path.is_empty().then_some(leaf);
+    }
+    let (sibling, path) = path.split_last()?;
+    let k = split(total);
+    if index < k {
+        Some(node_hash(&root_from_path(leaf, index, k, path)?, sibling))
+    } else {
+        Some(node_hash(
+            sibling,
+            &root_from_path(leaf, index - k, total - k, path)?,
+        ))
+    }
+}
+
+// Tree that only keeps the roots of its perfect subtrees so that
+// a leaf is appended in O(log n). Used for the nullifiers
+#[derive(Clone, Default, Debug)]
+pub struct MerkleFrontier {
+    // (size, root), largest first
+    nodes: Vec<(u64, Hash)>,
+    size: u64,
+}
+
+impl MerkleFrontier {
+    pub fn append(&mut self, leaf: Hash) {
+        let mut node = (1, leaf);
+        while let Some(&(size, left)) = self.nodes.last()
+            && size == node.0
+        {
+            self.nodes.pop();
+            node = (size * 2, node_hash(&left, &node.1));
+        }
+        self.nodes.push(node);
+        self.size += 1;
+    }
+
+    pub fn root(&self) -> Hash {
+        let mut nodes = self.nodes.iter().rev();
+        let Some(&(_, mut root)) = nodes.next() else {
+            return [0u8; 32];
+        };
+        for (_, left) in nodes {
+            root = node_hash(left, &root);
+        }
+        root
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+// Data of a proof op
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MerkleProof {
+    pub index: u64,
+    pub total: u64,
+    #[serde_as(as = "Vec<serde_with::hex::Hex>")]
+    pub path: Vec<Hash>,
+}
+
+pub fn proof_op(r#type: &str, key: &[u8], leaves: &[Hash], index: usize) -> ProofOp {
+    let proof = MerkleProof {
+        index: index as u64,
+        total: leaves.len() as u64,
+        path: path(leaves, index),
+    };
+    ProofOp {
+        r#type: r#type.to_string(),
+        key: key.to_vec().into(),
+        data: serde_json::to_vec(&proof).unwrap().into(),
+    }
+}
+
+// Apply the proof ops to a leaf, the result is the root of the
+// last tree. It must match the app hash of the block header
+pub fn verify_proof_ops(leaf: Hash, ops: &ProofOps) -> ZCVResult<Hash> {
+    let mut hash = leaf;
+    for op in ops.ops.iter() {
+        if op.r#type != PROOF_NULLIFIER && op.r#type != PROOF_STATE {
+            return Err(anyhow!("Unknown proof op {}", op.r#type).into());
+        }
+        let MerkleProof { index, total, path } = serde_json::from_slice(&op.data)?;
+        hash = root_from_path(hash, index, total, &path).ok_or(anyhow!("Invalid proof"))?;
+    }
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::merkle::{MerkleFrontier, path, root, root_from_path};
+
+    #[test]
+    fn test_merkle_tree() {
+        let leaves: Vec<_> = (0..20u8).map(|i| [i; 32]).collect();
+        let mut frontier = MerkleFrontier::default();
+        assert_eq!(frontier.root(), root(&[]));
+        for n in 1..=leaves.len() {
+            frontier.append(leaves[n - 1]);
+            let leaves = &leaves[..n];
+            let r = root(leaves);
+            assert_eq!(frontier.root(), r);
+            for (i, leaf) in leaves.iter().enumerate() {
+                let p = path(leaves, i);
+                assert_eq!(root_from_path(*leaf, i as u64, n as u64, &p), Some(r));
+                assert_ne!(root_from_path([99u8; 32], i as u64, n as u64, &p), Some(r));
+            }
+        }
+        assert_eq!(frontier.size(), 20);
+    }
+}
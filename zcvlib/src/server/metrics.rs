@@ -0,0 +1,276 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{
+        LazyLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{Router, http::header::CONTENT_TYPE, routing::get};
+use sqlx::{SqliteConnection, query_as};
+
+use crate::{ZCVResult, error::IntoAnyhow};
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+// Seconds
+const PROOF_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+const FINALIZE_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+#[derive(Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Default)]
+pub struct Gauge(AtomicU64);
+
+impl Gauge {
+    pub fn set(&self, v: u64) {
+        self.0.store(v, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Counter with one label
+#[derive(Default)]
+pub struct LabeledCounter(parking_lot::Mutex<BTreeMap<&'static str, u64>>);
+
+impl LabeledCounter {
+    pub fn inc(&self, label: &'static str) {
+        *self.0.lock().entry(label).or_default() += 1;
+    }
+
+    pub fn get(&self, label: &str) -> u64 {
+        self.0.lock().get(label).copied().unwrap_or_default()
+    }
+}
+
+pub struct Histogram {
+    buckets: &'static [f64],
+    // counts[i] is the number of observations <= buckets[i],
+    // the last one is for +Inf
+    data: parking_lot::Mutex<(Vec<u64>, f64)>,
+}
+
+impl Histogram {
+    pub fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            data: parking_lot::Mutex::new((vec![0; buckets.len() + 1], 0.0)),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let v = duration.as_secs_f64();
+        let mut data = self.data.lock();
+        let (counts, sum) = &mut *data;
+        for (i, b) in self.buckets.iter().enumerate() {
+            if v <= *b {
+                counts[i] += 1;
+            }
+        }
+        counts[self.buckets.len()] += 1;
+        *sum += v;
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let (counts, sum) = self.data.lock().clone();
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for (b, c) in self.buckets.iter().zip(counts.iter()) {
+            let _ = writeln!(out, "{name}_bucket{{le=\"{b}\"}} {c}");
+        }
+        let count = counts[self.buckets.len()];
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {count}");
+    }
+}
+
+pub struct Metrics {
+    pub block_height: Gauge,
+    pub vote_height: Gauge,
+    pub ballots: Counter,
+    pub actions: Counter,
+    // New txs only, the rechecks of the mempool are not counted
+    pub check_tx_accepted: Counter,
+    pub check_tx_rejected: LabeledCounter,
+    pub proof_verification: Histogram,
+    // Double spends between mempool txs, left out of the proposal
+    pub proposal_duplicates: Counter,
+    pub finalize_block: Histogram,
+    // Block time of the latest ballot, unix seconds
+    pub latest_ballot_time: Gauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            block_height: Gauge::default(),
+            vote_height: Gauge::default(),
+            ballots: Counter::default(),
+            actions: Counter::default(),
+            check_tx_accepted: Counter::default(),
+            check_tx_rejected: LabeledCounter::default(),
+            proof_verification: Histogram::new(PROOF_BUCKETS),
+            proposal_duplicates: Counter::default(),
+            finalize_block: Histogram::new(FINALIZE_BUCKETS),
+            latest_ballot_time: Gauge::default(),
+        }
+    }
+}
+
+fn render_value(out: &mut String, name: &str, kind: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+impl Metrics {
+    // Start the ballot counters from the committed chain
+    pub async fn load(&self, conn: &mut SqliteConnection) -> ZCVResult<()> {
+        let (ballots, actions): (u32, u32) =
+            query_as("SELECT (SELECT COUNT(*) FROM v_ballots), (SELECT COUNT(*) FROM v_actions)")
+                .fetch_one(&mut *conn)
+                .await?;
+        self.ballots.add(ballots as u64);
+        self.actions.add(actions as u64);
+        Ok(())
+    }
+
+    // Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        render_value(
+            &mut out,
+            "zcv_block_height",
+            "gauge",
+            "Height of the last committed block",
+            self.block_height.get(),
+        );
+        render_value(
+            &mut out,
+            "zcv_vote_height",
+            "gauge",
+            "Vote height of the last committed block",
+            self.vote_height.get(),
+        );
+        render_value(
+            &mut out,
+            "zcv_ballots_total",
+            "counter",
+            "Ballots committed",
+            self.ballots.get(),
+        );
+        render_value(
+            &mut out,
+            "zcv_actions_total",
+            "counter",
+            "Ballot actions committed",
+            self.actions.get(),
+        );
+        render_value(
+            &mut out,
+            "zcv_check_tx_accepted_total",
+            "counter",
+            "New txs accepted in the mempool",
+            self.check_tx_accepted.get(),
+        );
+        let _ = writeln!(
+            out,
+            "# HELP zcv_check_tx_rejected_total New txs rejected from the mempool"
+        );
+        let _ = writeln!(out, "# TYPE zcv_check_tx_rejected_total counter");
+        for (reason, count) in self.check_tx_rejected.0.lock().iter() {
+            let _ = writeln!(
+                out,
+                "zcv_check_tx_rejected_total{{reason=\"{reason}\"}} {count}"
+            );
+        }
+        self.proof_verification.render(
+            &mut out,
+            "zcv_proof_verification_seconds",
+            "Time to verify the proofs of a ballot",
+        );
+        render_value(
+            &mut out,
+            "zcv_proposal_duplicates_total",
+            "counter",
+            "Mempool double spends left out of a proposal",
+            self.proposal_duplicates.get(),
+        );
+        self.finalize_block.render(
+            &mut out,
+            "zcv_finalize_block_seconds",
+            "Time to finalize a block",
+        );
+        render_value(
+            &mut out,
+            "zcv_latest_ballot_timestamp_seconds",
+            "gauge",
+            "Block time of the latest ballot",
+            self.latest_ballot_time.get(),
+        );
+        out
+    }
+}
+
+// Serve GET /metrics until the process exits
+pub async fn serve_metrics(addr: SocketAddr) -> ZCVResult<()> {
+    let app = Router::new().route(
+        "/metrics",
+        get(|| async {
+            (
+                [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+                METRICS.render(),
+            )
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind(addr).await.anyhow()?;
+    tracing::info!("Metrics on {addr}");
+    axum::serve(listener, app).await.anyhow()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::server::metrics::Metrics;
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::default();
+        metrics.ballots.add(3);
+        metrics.check_tx_rejected.inc("duplicate");
+        metrics.check_tx_rejected.inc("duplicate");
+        metrics.proof_verification.observe(Duration::from_millis(300));
+        metrics.proof_verification.observe(Duration::from_secs(60));
+        let out = metrics.render();
+        assert!(out.contains("zcv_ballots_total 3\n"));
+        assert!(out.contains("zcv_check_tx_rejected_total{reason=\"duplicate\"} 2\n"));
+        assert!(out.contains("zcv_proof_verification_seconds_bucket{le=\"0.25\"} 0\n"));
+        assert!(out.contains("zcv_proof_verification_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(out.contains("zcv_proof_verification_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(out.contains("zcv_proof_verification_seconds_count 2\n"));
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::anyhow;
+use orchard_vote::BallotData;
+use serde_json::{Value, json};
+use sqlx::SqliteConnection;
+use tendermint_proto::crypto::ProofOps;
+use zcash_trees::warp::hasher::OrchardHasher;
+
+use crate::{
+    ZCVResult,
+    db::{
+        get_ballot_by_sighash, get_block_state, get_cmx_root_height, get_nullifier, list_nullifiers,
+    },
+    error::IntoAnyhow,
+    server::{
+        ElectionState, ServerState,
+        merkle::{PROOF_NULLIFIER, PROOF_STATE, nullifier_leaf, proof_op},
+    },
+};
+
+// ResponseQuery codes
+pub const QUERY_OK: u32 = 0;
+pub const QUERY_ERROR: u32 = 1;
+pub const QUERY_NOT_FOUND: u32 = 2;
+
+impl ServerState {
+    // Answer an ABCI query at the given block height (0 for the latest)
+    // Paths:
+    // - /ballot/<sighash>
+    // - /nullifier/<dnf>
+    // - /cmx_root/<root>
+    // - /election (only if the chain has a single election)
+    // - /election/<domain>
+    // - /elections
+    // - /state
+    // The election and state paths answer from the current state,
+    // at the latest height only
+    // Keys are hex encoded. Returns the block height of the answer,
+    // the JSON value if found and, if prove is set, the proof ops
+    // of the ballot, nullifier and election paths against the app hash.
+    // Proofs are only available at the latest height
+    pub async fn query(
+        &self,
+        path: &str,
+        height: u32,
+        prove: bool,
+    ) -> ZCVResult<(u32, Option<Value>, Option<ProofOps>)> {
+        let mut conn = self.pool.acquire().await?;
+        let (last_height, _) = get_block_state(&mut conn).await?;
+        let height = match height {
+            0 => last_height,
+            h if h > last_height => {
+                return Err(anyhow!("Height {h} is not committed yet").into());
+            }
+            h => h,
+        };
+
+        let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+        let current = matches!(
+            segments[..],
+            ["election"] | ["election", _] | ["elections"] | ["state"]
+        );
+        if current && height != last_height {
+            return Err(anyhow!("{path} is only available at the latest height").into());
+        }
+        if prove && height != last_height {
+            return Err(anyhow!("Proofs are only available at the latest height").into());
+        }
+        match segments[..] {
+            ["election"] => {
+                if self.elections.len() > 1 {
+                    return Err(anyhow!("Election domain required").into());
+                }
+                let value = self.elections.values().next().map(|e| self.election_json(e));
+                let proof = self.elections.keys().next().filter(|_| prove);
+                let proof = proof.map(|d| self.election_proof(d));
+                return Ok((height, value, proof));
+            }
+            ["election", domain] => {
+                let domain = hex::decode(domain).map_err(|_| anyhow!("Invalid domain"))?;
+                let domain = <[u8; 32]>::try_from(domain).ok();
+                let e = domain.and_then(|d| self.elections.get(&d));
+                let value = e.map(|e| self.election_json(e));
+                let proof = domain.filter(|_| prove && e.is_some());
+                let proof = proof.map(|d| self.election_proof(&d));
+                return Ok((height, value, proof));
+            }
+            ["elections"] => {
+                let elections: Vec<_> =
+                    self.elections.values().map(|e| self.election_json(e)).collect();
+                return Ok((height, Some(Value::Array(elections)), None));
+            }
+            _ => {}
+        }
+
+        if let ["state"] = segments[..] {
+            // Preimage of the app hash
+            let elections: Vec<_> = self
+                .elections
+                .iter()
+                .map(|(domain, e)| {
+                    json!({
+                        "domain": hex::encode(domain),
+                        "nf_root": hex::encode(e.nf_root.to_bytes()),
+                        "cmx_root": hex::encode(e.cmx_tree.root(&OrchardHasher::default())),
+                        "locked": e.locked,
+                    })
+                })
+                .collect();
+            let mut validators: Vec<_> = self.validators.iter().collect();
+            validators.sort();
+            let validators: Vec<_> = validators
+                .into_iter()
+                .map(|(pub_key, power)| {
+                    json!({
+                        "pub_key": hex::encode(pub_key),
+                        "power": power,
+                    })
+                })
+                .collect();
+            let value = json!({
+                "nf_root": hex::encode(self.nf_tree.root()),
+                "nf_count": self.nf_tree.size(),
+                "elections": elections,
+                "validators": validators,
+                "max_validators": self.max_validators,
+                "authority": hex::encode(self.authority.unwrap_or_default()),
+                "admin_nonce": self.admin_nonce,
+                "app_hash": hex::encode(self.apphash),
+            });
+            return Ok((height, Some(value), None));
+        }
+
+        // Rows are stored at the vote height of their election
+        let mut proof = None;
+        let value = match segments[..] {
+            ["ballot", sighash] => {
+                let sighash = hex::decode(sighash).map_err(|_| anyhow!("Invalid sighash"))?;
+                let ballot = get_ballot_by_sighash(&mut conn, &sighash, height).await?;
+                if prove && let Some(b) = &ballot {
+                    // The ballot is proven by the leaf of its first nullifier
+                    let data = BallotData::read(b.ballot.as_slice()).anyhow()?;
+                    if let Some(a) = data.actions.first() {
+                        proof = Some(self.nullifier_proof(&mut conn, &a.nf).await?);
+                    }
+                }
+                ballot.map(|b| {
+                    json!({
+                        "height": b.height,
+                        "itx": b.itx,
+                        "ballot": hex::encode(&b.ballot),
+                    })
+                })
+            }
+            ["nullifier", dnf] => {
+                let dnf = hex::decode(dnf).map_err(|_| anyhow!("Invalid nullifier"))?;
+                let nullifier = get_nullifier(&mut conn, &dnf, height).await?;
+                if prove && nullifier.is_some() {
+                    proof = Some(self.nullifier_proof(&mut conn, &dnf).await?);
+                }
+                nullifier.map(|(height, sighash)| {
+                    json!({
+                        "height": height,
+                        "ballot": hex::encode(sighash),
+                    })
+                })
+            }
+            ["cmx_root", root] => {
+                let root = hex::decode(root).map_err(|_| anyhow!("Invalid cmx root"))?;
+                get_cmx_root_height(&mut conn, &root, height)
+                    .await?
+                    .map(|(domain, height)| {
+                        json!({
+                            "domain": hex::encode(domain),
+                            "height": height,
+                        })
+                    })
+            }
+            _ => return Err(anyhow!("Unknown query path {path}").into()),
+        };
+        Ok((height, value, proof))
+    }
+
+    fn election_json(&self, e: &ElectionState) -> Value {
+        json!({
+            "election": e.election,
+            "domain": hex::encode(&e.election.domain),
+            "nf_root": hex::encode(e.nf_root.to_bytes()),
+            "cmx_root": hex::encode(e.cmx_tree.root(&OrchardHasher::default())),
+            "locked": e.locked,
+        })
+    }
+
+    // Elections follow the root of the nullifier tree in the state leaves
+    fn election_proof(&self, domain: &[u8; 32]) -> ProofOps {
+        let index = 1 + self.elections.keys().position(|d| d == domain).unwrap();
+        ProofOps {
+            ops: vec![proof_op(PROOF_STATE, domain, &self.state_leaves(), index)],
+        }
+    }
+
+    // From the leaf of the nullifier to the root of the nullifier tree,
+    // then to the app hash
+    async fn nullifier_proof(
+        &self,
+        conn: &mut SqliteConnection,
+        dnf: &[u8],
+    ) -> ZCVResult<ProofOps> {
+        let nullifiers = list_nullifiers(conn).await?;
+        let index = nullifiers
+            .iter()
+            .position(|(d, _)| d == dnf)
+            .ok_or(anyhow!("Unknown nullifier"))?;
+        let leaves: Vec<_> = nullifiers
+            .iter()
+            .map(|(dnf, sighash)| nullifier_leaf(dnf, sighash))
+            .collect();
+        Ok(ProofOps {
+            ops: vec![
+                proof_op(PROOF_NULLIFIER, dnf, &leaves, index),
+                proof_op(PROOF_STATE, b"nullifiers", &self.state_leaves(), 0),
+            ],
+        })
+    }
+}
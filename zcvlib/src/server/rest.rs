@@ -0,0 +1,315 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{
+    Json, Router,
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderName, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tonic::{Code, Request, Status};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::{
+    ZCVResult,
+    db::{list_ballot_page, resolve_domain},
+    error::IntoAnyhow,
+    server::rpc::ZCVServer,
+    vote_rpc::{Ballot, ElectionId, Empty, StatsRequest, vote_streamer_server::VoteStreamer},
+};
+
+// Ballots returned by GET /v1/ballots when no limit is given,
+// and the largest page allowed
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+// CORS policy of the gRPC-Web and REST endpoints. No origins (or "*")
+// allows every origin
+pub fn cors_layer(origins: &[String]) -> ZCVResult<CorsLayer> {
+    let allow_origin = if origins.is_empty() || origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = origins
+            .iter()
+            .map(|o| o.parse::<HeaderValue>())
+            .collect::<Result<Vec<_>, _>>()
+            .anyhow()?;
+        AllowOrigin::list(origins)
+    };
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_headers(Any)
+        // gRPC-Web clients read the status from these headers
+        .expose_headers([
+            HeaderName::from_static("grpc-status"),
+            HeaderName::from_static("grpc-message"),
+            HeaderName::from_static("grpc-status-details-bin"),
+        ])
+        .max_age(Duration::from_secs(24 * 3600)))
+}
+
+// tonic status as a JSON error with the matching HTTP code
+pub struct RestError(Status);
+
+impl From<Status> for RestError {
+    fn from(status: Status) -> Self {
+        Self(status)
+    }
+}
+
+impl IntoResponse for RestError {
+    fn into_response(self) -> Response {
+        let code = match self.0.code() {
+            Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+                StatusCode::BAD_REQUEST
+            }
+            Code::NotFound => StatusCode::NOT_FOUND,
+            Code::AlreadyExists => StatusCode::CONFLICT,
+            Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+            Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Code::PermissionDenied => StatusCode::FORBIDDEN,
+            Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (code, Json(json!({ "error": self.0.message() }))).into_response()
+    }
+}
+
+type RestResult = Result<Json<Value>, RestError>;
+
+// Election selected by the hex "domain" query parameter.
+// It may be left out if the chain has a single election
+#[derive(Deserialize)]
+pub struct DomainQuery {
+    pub domain: Option<String>,
+}
+
+fn decode_domain(domain: Option<&str>) -> Result<Vec<u8>, Status> {
+    domain
+        .map(hex::decode)
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(|_| Status::invalid_argument("Invalid domain hex"))
+}
+
+fn election_json(e: &crate::vote_rpc::Election) -> RestResult {
+    let election: Value =
+        serde_json::from_str(&e.election).map_err(|e| Status::internal(e.to_string()))?;
+    Ok(Json(json!({
+        "election": election,
+        "nf_root": hex::encode(&e.nf_root),
+        "cmx_tree_state": hex::encode(&e.cmx_tree_state),
+    })))
+}
+
+async fn get_election(
+    State(service): State<Arc<ZCVServer>>,
+    Query(q): Query<DomainQuery>,
+) -> RestResult {
+    let domain = decode_domain(q.domain.as_deref())?;
+    let e = service
+        .get_election(Request::new(ElectionId { domain }))
+        .await?
+        .into_inner();
+    election_json(&e)
+}
+
+async fn list_elections(State(service): State<Arc<ZCVServer>>) -> RestResult {
+    let elections = service
+        .list_elections(Request::new(Empty {}))
+        .await?
+        .into_inner()
+        .elections;
+    let elections = elections
+        .iter()
+        .map(|e| election_json(e).map(|Json(v)| v))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Json(json!({ "elections": elections })))
+}
+
+async fn get_latest_vote_height(
+    State(service): State<Arc<ZCVServer>>,
+    Query(q): Query<DomainQuery>,
+) -> RestResult {
+    let domain = decode_domain(q.domain.as_deref())?;
+    let h = service
+        .get_latest_vote_height(Request::new(ElectionId { domain }))
+        .await?
+        .into_inner();
+    Ok(Json(json!({
+        "height": h.height,
+        "hash": hex::encode(h.hash),
+        "block_height": h.block_height,
+        "cmx_root": hex::encode(h.cmx_root),
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct BallotPage {
+    pub domain: Option<String>,
+    pub start: u32,
+    pub end: u32,
+    pub after_height: Option<u32>,
+    pub after_itx: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+// Paginated GetVoteRange. "next" is the cursor of the following page,
+// null on the last page
+async fn get_vote_range(
+    State(service): State<Arc<ZCVServer>>,
+    Query(page): Query<BallotPage>,
+) -> RestResult {
+    let limit = page.limit.unwrap_or(MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let after = page.after_height.zip(page.after_itx);
+    let domain = decode_domain(page.domain.as_deref())?;
+    let pool = {
+        let c = service.context.lock().await;
+        c.context.pool.clone()
+    };
+    let res = async {
+        let mut conn = pool.acquire().await?;
+        let domain = resolve_domain(&mut conn, &domain).await?;
+        list_ballot_page(&mut conn, &domain, page.start, page.end, after, limit).await
+    };
+    let ballots = res.await.map_err(|e| Status::internal(e.to_string()))?;
+    let next = ballots
+        .last()
+        .filter(|_| ballots.len() == limit as usize)
+        .map(|b| json!({ "after_height": b.height, "after_itx": b.itx }));
+    let ballots = ballots
+        .iter()
+        .map(|b| {
+            json!({
+                "height": b.height,
+                "itx": b.itx,
+                "ballot": hex::encode(&b.ballot),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(Json(json!({ "ballots": ballots, "next": next })))
+}
+
+#[derive(Deserialize)]
+pub struct StatsQuery {
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub bucket_size: u32,
+}
+
+async fn get_election_stats(
+    State(service): State<Arc<ZCVServer>>,
+    Query(q): Query<StatsQuery>,
+) -> RestResult {
+    let domain = decode_domain(q.domain.as_deref())?;
+    let stats = service
+        .get_election_stats(Request::new(StatsRequest {
+            bucket_size: q.bucket_size,
+            domain,
+        }))
+        .await?
+        .into_inner();
+    let buckets = stats
+        .buckets
+        .iter()
+        .map(|b| json!({ "start": b.start, "ballots": b.ballots }))
+        .collect::<Vec<_>>();
+    Ok(Json(json!({
+        "ballots": stats.ballots,
+        "actions": stats.actions,
+        "buckets": buckets,
+        "first_height": stats.first_height,
+        "last_height": stats.last_height,
+        "cmx_tree_size": stats.cmx_tree_size,
+        "cmx_roots": stats.cmx_roots,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct SubmitBallot {
+    // Hex encoded ballot
+    pub ballot: String,
+    #[serde(default)]
+    pub pow_nonce: u64,
+}
+
+async fn submit_vote(
+    State(service): State<Arc<ZCVServer>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(body): Json<SubmitBallot>,
+) -> RestResult {
+    service.check_rate(Some(addr.ip()))?;
+    let ballot =
+        hex::decode(&body.ballot).map_err(|_| Status::invalid_argument("Invalid ballot hex"))?;
+    let hash = service
+        .submit_ballot(Ballot {
+            ballot,
+            pow_nonce: body.pow_nonce,
+            ..Default::default()
+        })
+        .await?;
+    Ok(Json(json!({ "hash": hex::encode(hash.hash) })))
+}
+
+// JSON mapping of the VoteStreamer for clients without gRPC
+pub fn rest_router(service: Arc<ZCVServer>) -> Router {
+    Router::new()
+        .route("/v1/election", get(get_election))
+        .route("/v1/elections", get(list_elections))
+        .route("/v1/height", get(get_latest_vote_height))
+        .route("/v1/ballots", get(get_vote_range).post(submit_vote))
+        .route("/v1/stats", get(get_election_stats))
+        .with_state(service)
+}
+
+// Serve the REST gateway until shutdown completes
+pub async fn serve_rest(
+    service: Arc<ZCVServer>,
+    addr: SocketAddr,
+    cors: CorsLayer,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> ZCVResult<()> {
+    let app = rest_router(service).layer(cors);
+    let listener = tokio::net::TcpListener::bind(addr).await.anyhow()?;
+    tracing::info!("REST gateway on {addr}");
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown)
+    .await
+    .anyhow()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, response::IntoResponse};
+    use tonic::Status;
+
+    use crate::server::rest::{RestError, cors_layer, decode_domain};
+
+    #[test]
+    fn test_rest_error() {
+        let code = |status: Status| RestError(status).into_response().status();
+        assert_eq!(code(Status::invalid_argument("")), StatusCode::BAD_REQUEST);
+        assert_eq!(code(Status::resource_exhausted("")), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(code(Status::internal("")), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_decode_domain() {
+        assert_eq!(decode_domain(None).unwrap(), Vec::<u8>::new());
+        assert_eq!(decode_domain(Some("0102")).unwrap(), vec![1u8, 2]);
+        assert!(decode_domain(Some("xyz")).is_err());
+    }
+
+    #[test]
+    fn test_cors_origins() {
+        assert!(cors_layer(&[]).is_ok());
+        assert!(cors_layer(&["https://vote.example.com".to_string()]).is_ok());
+        assert!(cors_layer(&["bad\norigin".to_string()]).is_err());
+    }
+}
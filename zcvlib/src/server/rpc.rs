@@ -1,13 +1,9 @@
 #[cfg(feature = "server")]
-use std::{pin::Pin, sync::Arc};
+use std::{net::IpAddr, sync::Arc, time::Instant};
 
-#[cfg(feature = "server")]
-use anyhow::Context;
 #[cfg(feature = "server")]
 use prost::Message;
 #[cfg(feature = "server")]
-use sqlx::query_as;
-#[cfg(feature = "server")]
 use tokio::sync::{Mutex, mpsc};
 #[cfg(feature = "server")]
 use tokio_stream::wrappers::ReceiverStream;
@@ -16,32 +12,97 @@ use tonic::{Request, Response, Status, async_trait};
 
 #[cfg(feature = "server")]
 use crate::{
-    ZCVError, ZCVResult,
+    ZCVError,
     context::BFTContext,
-    db::get_election as fetch_election,
+    db::{
+        get_ballot_by_sighash, get_ballot_range, get_block_hash, get_cmx_roots,
+        get_election_stats, get_vote_election, get_vote_height, list_ballots,
+        list_vote_elections, resolve_domain,
+    },
     error::IntoAnyhow,
-    lwd::fetch_initial_roots,
-    pod::ElectionPropsPub,
-    server::submit_tx,
+    server::{
+        admission::RateLimiter,
+        status::TrackedBallot,
+        submit_tx,
+    },
     vote_rpc::{
-        Ballot, Election, Empty, Hash, Validator, VoteHeight, VoteMessage, VoteRange,
+        Ballot, BallotStatus, CmxRoot, CmxRoots, Election, ElectionId, ElectionStats, Elections,
+        Empty, Hash, StatsRequest, VoteCursor, VoteHeight, VoteMessage, VoteRange, VoteRangeItem,
+        VoteSubscription,
+        ballot_status::Status as BallotStatusCode, vote_range_item::Item,
         vote_message::TypeOneof, vote_streamer_server::VoteStreamer,
     },
 };
 
+// Vote heights read at once by a subscription
+#[cfg(feature = "server")]
+const SUBSCRIBE_BATCH: u32 = 100;
+// Ballots buffered ahead of a GetVoteRange client
+#[cfg(feature = "server")]
+const RANGE_CHANNEL_SIZE: usize = 64;
+// Vote heights per bucket of GetElectionStats by default
+#[cfg(feature = "server")]
+pub const STATS_BUCKET_SIZE: u32 = 100;
+
 #[cfg(feature = "server")]
 pub struct ZCVServer {
     pub context: Arc<Mutex<BFTContext>>,
+    limiter: parking_lot::Mutex<RateLimiter>,
+}
+
+#[cfg(feature = "server")]
+impl ZCVServer {
+    // submit_rate: ballots per minute per client IP, 0 for no limit
+    pub fn new(context: Arc<Mutex<BFTContext>>, submit_rate: u32) -> Self {
+        Self {
+            context,
+            limiter: parking_lot::Mutex::new(RateLimiter::new(submit_rate)),
+        }
+    }
+
+    // Count a ballot submitted by the client IP against the submit rate.
+    // Requests without a remote address share one bucket
+    pub fn check_rate(&self, ip: Option<IpAddr>) -> Result<(), Status> {
+        if !self.limiter.lock().check(ip, Instant::now()) {
+            return Err(Status::resource_exhausted(
+                "Too many ballots submitted, try again later",
+            ));
+        }
+        Ok(())
+    }
+
+    // Submit a ballot whose client was already counted by check_rate
+    pub async fn submit_ballot(&self, ballot: Ballot) -> Result<Hash, Status> {
+        let res = async move {
+            let m = VoteMessage {
+                type_oneof: Some(TypeOneof::Ballot(ballot)),
+            };
+            let json = submit_message(&self.context, m).await?;
+            let hash = json
+                .pointer("/result/data")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            Ok(Hash {
+                hash: hex::decode(hash)?,
+            })
+        };
+        res.await.map_err(to_tonic)
+    }
 }
 
 #[cfg(feature = "server")]
 #[async_trait]
 impl VoteStreamer for ZCVServer {
-    async fn get_election(&self, _request: Request<Empty>) -> Result<Response<Election>, Status> {
+    async fn get_election(
+        &self,
+        request: Request<ElectionId>,
+    ) -> Result<Response<Election>, Status> {
         let res = async move {
+            let ElectionId { domain } = request.into_inner();
             let c = self.context.lock().await;
             let mut conn = c.connect().await?;
-            let (e, nf_root, cmx_tree_state) = fetch_election(&mut conn).await?;
+            let domain = resolve_domain(&mut conn, &domain).await?;
+            let (e, nf_root, cmx_tree_state) = get_vote_election(&mut conn, &domain).await?;
             let election = Election {
                 election: serde_json::to_string(&e)?,
                 nf_root,
@@ -52,143 +113,302 @@ impl VoteStreamer for ZCVServer {
         res.await.map_err(to_tonic)
     }
 
-    async fn set_election(&self, request: Request<Election>) -> Result<Response<Hash>, Status> {
+    async fn list_elections(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Elections>, Status> {
         let res = async move {
-            let election = request.into_inner();
-            let e: ElectionPropsPub = serde_json::from_str(&election.election).unwrap();
-            let election = {
-                let c = self.context.lock().await;
-                let (nf_root, cmx_tree_state) = fetch_initial_roots(&c.context.lwd_url, &e.pir, e.end).await?;
-                Election {
+            let c = self.context.lock().await;
+            let mut conn = c.connect().await?;
+            let mut elections = vec![];
+            for (e, ..) in list_vote_elections(&mut conn).await? {
+                let (e, nf_root, cmx_tree_state) = get_vote_election(&mut conn, &e.domain).await?;
+                elections.push(Election {
+                    election: serde_json::to_string(&e)?,
                     nf_root,
                     cmx_tree_state,
-                    ..election
-                }
-            };
-
-            let m = VoteMessage {
-                type_oneof: Some(TypeOneof::SetElection(election)),
-            };
-            let rep = self.submit(m).await?;
-            let hash = rep
-                .pointer("/result")
-                .and_then(|v| v.as_str())
-                .unwrap_or_default();
-            Ok::<_, anyhow::Error>(Response::new(Hash {
-                hash: hex::decode(hash)?,
-            }))
+                });
+            }
+            Ok::<_, anyhow::Error>(Response::new(Elections { elections }))
         };
         res.await.map_err(to_tonic)
     }
 
-    async fn add_validator(&self, request: Request<Validator>) -> Result<Response<Empty>, Status> {
-        let res = async move {
-            let validator = request.into_inner();
-            let m = VoteMessage {
-                type_oneof: Some(TypeOneof::AddValidator(validator)),
-            };
-            self.submit(m).await?;
-            Ok::<_, anyhow::Error>(Response::new(Empty {}))
-        };
-        res.await.map_err(to_tonic)
-    }
-
-    async fn lock(&self, _request: Request<Empty>) -> Result<Response<Empty>, Status> {
-        let m = VoteMessage {
-            type_oneof: Some(TypeOneof::Lock(Empty {})),
-        };
-        self.submit(m).await?;
-        Ok(Response::new(Empty {}))
-    }
-
     async fn get_latest_vote_height(
         &self,
-        _request: Request<Empty>,
+        request: Request<ElectionId>,
     ) -> Result<Response<VoteHeight>, Status> {
         let res = async move {
+            let ElectionId { domain } = request.into_inner();
             let c = self.context.lock().await;
             let mut conn = c.connect().await?;
-            let (height, ): (u32, ) = query_as(
-                "SELECT s.height FROM v_state s WHERE s.id = 0",
-            )
-            .fetch_one(&mut *conn)
-            .await
-            .context("get latest vote height")?;
-            Ok::<_, anyhow::Error>(Response::new(VoteHeight { height, hash: vec![] }))
+            let domain = resolve_domain(&mut conn, &domain).await?;
+            let height = get_vote_height(&mut conn, &domain).await?;
+            let (block_height, hash) = get_block_hash(&mut conn).await?;
+            // The cmx tree only changes with ballots
+            let cmx_root = get_cmx_roots(&mut conn, &domain, height, height)
+                .await?
+                .pop()
+                .map(|(_, root)| root)
+                .unwrap_or_default();
+            Ok::<_, anyhow::Error>(Response::new(VoteHeight {
+                height,
+                hash,
+                cmx_root,
+                block_height,
+            }))
         };
         res.await.map_err(to_tonic)
     }
 
-    type GetVoteRangeStream = ReceiverStream<Result<Ballot, Status>>;
+    type GetVoteRangeStream = ReceiverStream<Result<VoteRangeItem, Status>>;
 
+    // Ends with the trailer if every ballot was sent,
+    // or with an error status
     async fn get_vote_range(
         &self,
         request: Request<VoteRange>,
     ) -> Result<Response<Self::GetVoteRangeStream>, Status> {
+        let VoteRange {
+            start,
+            end,
+            after,
+            domain,
+        } = request.into_inner();
+        let pool = {
+            let c = self.context.lock().await;
+            c.context.pool.clone()
+        };
+        let domain = select_domain(&pool, &domain).await?;
+        let (tx, rx) = mpsc::channel::<Result<VoteRangeItem, Status>>(RANGE_CHANNEL_SIZE);
+        tokio::spawn(async move {
+            let after = after.map(|c| (c.height, c.itx));
+            let res = async {
+                let mut conn = pool.acquire().await?;
+                get_ballot_range(&mut conn, &domain, start, end, after, |b| {
+                    let tx = tx.clone();
+                    async move {
+                        let item = VoteRangeItem {
+                            item: Some(Item::Ballot(b)),
+                        };
+                        tx.send(Ok(item))
+                            .await
+                            .map_err(|_| anyhow::anyhow!("Client disconnected"))?;
+                        Ok::<_, ZCVError>(())
+                    }
+                })
+                .await
+            };
+            let item = match res.await {
+                Ok(last) => {
+                    let (height, itx) = last.unwrap_or_default();
+                    Ok(VoteRangeItem {
+                        item: Some(Item::End(VoteCursor { height, itx })),
+                    })
+                }
+                Err(e) => {
+                    tracing::warn!("get_vote_range [{start},{end}]: {e}");
+                    Err(Status::internal(e.to_string()))
+                }
+            };
+            let _ = tx.send(item).await;
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn submit_vote(&self, request: tonic::Request<Ballot>) -> Result<Response<Hash>, Status> {
+        self.check_rate(request.remote_addr().map(|addr| addr.ip()))?;
+        let hash = self.submit_ballot(request.into_inner()).await?;
+        Ok(Response::new(hash))
+    }
+
+    // Included ballots are in the database. The others are tracked
+    // from check_tx and finalize_block
+    async fn get_ballot_status(
+        &self,
+        request: Request<Hash>,
+    ) -> Result<Response<BallotStatus>, Status> {
         let res = async move {
-            let request = request.into_inner();
-            let VoteRange { start, end } = request;
-            let conn = {
-                let c = self.context.lock().await;
-                c.context.pool.acquire().await?.detach()
+            let Hash { hash } = request.into_inner();
+            let c = self.context.lock().await;
+            let mut conn = c.connect().await?;
+            if let Some(ballot) = get_ballot_by_sighash(&mut conn, &hash, u32::MAX).await? {
+                return Ok::<_, anyhow::Error>(BallotStatus {
+                    status: BallotStatusCode::Included as i32,
+                    height: ballot.height,
+                    itx: ballot.itx,
+                    ..BallotStatus::default()
+                });
+            }
+            let tracked = c.ballot_tracker.lock().get(&hash);
+            let status = match tracked {
+                Some(TrackedBallot::Pending) => BallotStatus {
+                    status: BallotStatusCode::Pending as i32,
+                    ..BallotStatus::default()
+                },
+                Some(TrackedBallot::Rejected { height, reason }) => BallotStatus {
+                    status: BallotStatusCode::Rejected as i32,
+                    height,
+                    reason,
+                    ..BallotStatus::default()
+                },
+                None => BallotStatus::default(),
             };
-            let (tx, rx) = mpsc::channel::<Result<Ballot, Status>>(1);
-            let handler = move |b| -> Pin<Box<dyn Future<Output = ZCVResult<()>> + Send>> {
-                let tx2 = tx.clone();
-                Box::pin(async move {
-                    let _ = tx2.send(Ok(b)).await;
-                    Ok::<_, ZCVError>(())
-                })
+            Ok(status)
+        };
+        res.await.map(Response::new).map_err(to_tonic)
+    }
+
+    type SubscribeVotesStream = ReceiverStream<Result<Ballot, Status>>;
+
+    // Send the ballots already stored, then wait for the next
+    // commit and send the new ones. Ends when the client disconnects
+    async fn subscribe_votes(
+        &self,
+        request: Request<VoteSubscription>,
+    ) -> Result<Response<Self::SubscribeVotesStream>, Status> {
+        let VoteSubscription { start, domain } = request.into_inner();
+        let (pool, mut notify) = {
+            let c = self.context.lock().await;
+            (c.context.pool.clone(), c.block_notify.subscribe())
+        };
+        let (domain, election) = {
+            let res = async {
+                let mut conn = pool.acquire().await?;
+                let domain = resolve_domain(&mut conn, &domain).await?;
+                let (election, ..) = get_vote_election(&mut conn, &domain).await?;
+                Ok::<_, ZCVError>((domain, election))
             };
-            crate::db::get_ballot_range(conn, start, end, handler).await?;
-            let rx = ReceiverStream::new(rx);
-            let res = Response::new(rx);
+            res.await.anyhow().map_err(to_tonic)?
+        };
+        // The first vote height of an election is after its snapshot height
+        let start = start.max(election.end + 1);
+        if let Some(close) = election.closing_vote_height()
+            && start > close
+        {
+            return Err(Status::invalid_argument(format!(
+                "start {start} is after the last vote height {close}"
+            )));
+        }
+        let (tx, rx) = mpsc::channel::<Result<Ballot, Status>>(SUBSCRIBE_BATCH as usize);
+        tokio::spawn(async move {
+            let mut next = start;
+            loop {
+                let res = async {
+                    let mut conn = pool.acquire().await?;
+                    let end = get_vote_height(&mut conn, &domain).await?;
+                    while next <= end {
+                        let to = end.min(next.saturating_add(SUBSCRIBE_BATCH - 1));
+                        for ballot in list_ballots(&mut conn, &domain, next, to).await? {
+                            if tx.send(Ok(ballot)).await.is_err() {
+                                return Ok(false);
+                            }
+                        }
+                        next = to + 1;
+                    }
+                    Ok::<_, ZCVError>(true)
+                };
+                match res.await {
+                    Ok(true) => {}
+                    Ok(false) => return,
+                    Err(e) => {
+                        let _ = tx.send(Err(Status::internal(e.to_string()))).await;
+                        return;
+                    }
+                }
+                tokio::select! {
+                    changed = notify.changed() => {
+                        // The ABCI app has stopped
+                        if changed.is_err() {
+                            return;
+                        }
+                    }
+                    _ = tx.closed() => return,
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
 
-            Ok::<_, anyhow::Error>(res)
+    // Voters check that the anchor of their ballot is one of these
+    async fn get_cmx_roots(
+        &self,
+        request: Request<VoteRange>,
+    ) -> Result<Response<CmxRoots>, Status> {
+        let res = async move {
+            let VoteRange {
+                start, end, domain, ..
+            } = request.into_inner();
+            let c = self.context.lock().await;
+            let mut conn = c.connect().await?;
+            let domain = resolve_domain(&mut conn, &domain).await?;
+            let roots = get_cmx_roots(&mut conn, &domain, start, end)
+                .await?
+                .into_iter()
+                .map(|(height, root)| CmxRoot { height, root })
+                .collect();
+            Ok::<_, anyhow::Error>(Response::new(CmxRoots { roots }))
         };
         res.await.map_err(to_tonic)
     }
 
-    async fn submit_vote(&self, request: tonic::Request<Ballot>) -> Result<Response<Hash>, Status> {
+    async fn get_election_stats(
+        &self,
+        request: Request<StatsRequest>,
+    ) -> Result<Response<ElectionStats>, Status> {
         let res = async move {
-            let ballot = request.into_inner();
-            let m = VoteMessage {
-                type_oneof: Some(TypeOneof::Ballot(ballot)),
-            };
-            let json = self.submit(m).await?;
-            let hash = json
-                .pointer("/result/data")
-                .and_then(|v| v.as_str())
-                .unwrap_or_default();
-            let hash = Hash {
-                hash: hex::decode(hash)?,
+            let StatsRequest {
+                bucket_size,
+                domain,
+            } = request.into_inner();
+            let bucket_size = if bucket_size == 0 {
+                STATS_BUCKET_SIZE
+            } else {
+                bucket_size
             };
-            Ok(Response::new(hash))
+            let c = self.context.lock().await;
+            let mut conn = c.connect().await?;
+            let domain = resolve_domain(&mut conn, &domain).await?;
+            let stats = get_election_stats(&mut conn, &domain, bucket_size).await?;
+            Ok::<_, anyhow::Error>(Response::new(stats))
         };
         res.await.map_err(to_tonic)
     }
 }
 
-impl ZCVServer {
-    async fn submit(&self, m: VoteMessage) -> Result<serde_json::Value, Status> {
-        let comet_port = {
-            let c = self.context.lock().await;
-            c.cometrpc_port
-        };
-        let res = submit_tx(m.encode_to_vec().as_slice(), comet_port)
-            .await
-            .anyhow()
-            .map_err(to_tonic)?;
-        if res.pointer("/error").is_some() {
-            let error_message = res
-                .pointer("/error/message")
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-            return Err(Status::internal(error_message));
-        }
-        Ok(res)
+// Election of a streaming request, resolved before the stream starts
+#[cfg(feature = "server")]
+async fn select_domain(pool: &sqlx::SqlitePool, domain: &[u8]) -> Result<Vec<u8>, Status> {
+    let res = async {
+        let mut conn = pool.acquire().await?;
+        resolve_domain(&mut conn, domain).await
+    };
+    res.await.anyhow().map_err(to_tonic)
+}
+
+// Broadcast a message through the CometBFT RPC
+#[cfg(feature = "server")]
+pub async fn submit_message(
+    context: &Mutex<BFTContext>,
+    m: VoteMessage,
+) -> Result<serde_json::Value, Status> {
+    let comet_port = {
+        let c = context.lock().await;
+        c.cometrpc_port
+    };
+    let res = submit_tx(m.encode_to_vec().as_slice(), comet_port)
+        .await
+        .anyhow()
+        .map_err(to_tonic)?;
+    if res.pointer("/error").is_some() {
+        let error_message = res
+            .pointer("/error/message")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        return Err(Status::internal(error_message));
     }
+    Ok(res)
 }
 
 #[cfg(feature = "server")]
@@ -198,3 +418,81 @@ pub fn to_tonic(e: anyhow::Error) -> tonic::Status {
     }
     tonic::Status::internal(e.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use anyhow::Result;
+    use tokio::sync::Mutex;
+    use tokio_stream::StreamExt;
+    use tonic::Request;
+    use zcash_protocol::consensus::Network;
+
+    use crate::{
+        context::BFTContext,
+        db::{
+            create_schema, drop_schema, get_election, store_ballot, store_vote_election,
+            store_vote_height,
+        },
+        server::rpc::ZCVServer,
+        tests::test_setup,
+        vote::mint,
+        vote_rpc::{VoteSubscription, vote_streamer_server::VoteStreamer},
+    };
+
+    #[tokio::test]
+    async fn test_subscribe_votes() -> Result<()> {
+        let ctx = BFTContext::new("rpc-test.db", "", 0, true).await?;
+        let mut conn = ctx.connect().await?;
+        drop_schema(&mut conn).await?;
+        create_schema(&mut conn).await?;
+        test_setup(&mut conn).await?;
+        let (mut election, ..) = get_election(&mut conn).await?;
+        election.close_height = election.end + 10;
+        store_vote_election(&mut conn, &election, &[0u8; 32], &[]).await?;
+        let domain = election.domain.clone();
+        let end = election.end;
+        for h in 1..=2 {
+            let ballot = mint(&Network::MainNetwork, &mut conn, 0, 1000).await?;
+            store_ballot(&mut conn, end + h, 0, ballot).await?;
+            store_vote_height(&mut conn, &domain, end + h).await?;
+        }
+
+        let notify = ctx.block_notify.clone();
+        let service = ZCVServer::new(Arc::new(Mutex::new(ctx)), 0);
+        let subscribe = |start: u32| {
+            service.subscribe_votes(Request::new(VoteSubscription {
+                start,
+                domain: domain.clone(),
+            }))
+        };
+        let timeout = Duration::from_secs(10);
+        // Clamped to the first vote height
+        let mut ballots = subscribe(0).await?.into_inner();
+        let b = tokio::time::timeout(timeout, ballots.next()).await?.unwrap()?;
+        assert_eq!(b.height, end + 1);
+        // After the last vote height of the election
+        let err = subscribe(end + 11).await.err().unwrap();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        let mut ballots = service
+            .subscribe_votes(Request::new(VoteSubscription {
+                start: end + 2,
+                domain: domain.clone(),
+            }))
+            .await?
+            .into_inner();
+        let b = tokio::time::timeout(timeout, ballots.next()).await?.unwrap()?;
+        assert_eq!(b.height, end + 2);
+
+        // Pushed after the next commit
+        let ballot = mint(&Network::MainNetwork, &mut conn, 0, 1000).await?;
+        store_ballot(&mut conn, end + 3, 0, ballot).await?;
+        store_vote_height(&mut conn, &domain, end + 3).await?;
+        notify.send_replace(3);
+        let b = tokio::time::timeout(timeout, ballots.next()).await?.unwrap()?;
+        assert_eq!(b.height, end + 3);
+        Ok(())
+    }
+}
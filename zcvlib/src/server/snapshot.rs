@@ -0,0 +1,358 @@
+use anyhow::anyhow;
+use bincode::{Decode, Encode};
+use blake2b_simd::Params;
+use orchard::tree::MerkleHashOrchard;
+use sqlx::{Column, Connection, Row, SqliteConnection, SqlitePool, TypeInfo, ValueRef, query};
+use tendermint_proto::abci::{
+    ResponseApplySnapshotChunk, ResponseOfferSnapshot, Snapshot, response_apply_snapshot_chunk,
+    response_offer_snapshot,
+};
+use zcash_trees::warp::hasher::OrchardHasher;
+
+use crate::{
+    ZCVResult,
+    db::{
+        clear_cmx_roots, create_schema, drop_schema, get_block_state, get_snapshot_chunk,
+        get_vote_election, list_action_cmxs, list_snapshots, list_vote_elections, prune_snapshots,
+        store_cmx_root, store_snapshot, store_vote_frontier, store_vote_height,
+    },
+    error::IntoAnyhow,
+    server::{ServerState, read_roots},
+};
+
+pub const SNAPSHOT_FORMAT: u32 = 1;
+// Take a snapshot every SNAPSHOT_INTERVAL blocks
+pub const SNAPSHOT_INTERVAL: u32 = 1000;
+pub const SNAPSHOT_KEEP: u32 = 2;
+pub const SNAPSHOT_CHUNK_SIZE: usize = 1 << 20;
+
+// Tables that make up the state of the vote chain
+const SNAPSHOT_TABLES: &[&str] = &[
+    "v_state",
+    "v_elections",
+    "v_ballots",
+    "v_actions",
+    "vs_cmxs",
+    "v_validators",
+];
+
+#[derive(Encode, Decode, Debug)]
+enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+#[derive(Encode, Decode, Debug)]
+struct TableDump {
+    name: String,
+    columns: Vec<String>,
+    rows: Vec<Vec<SqlValue>>,
+}
+
+// Snapshot being restored from the chunks sent by our peers
+pub struct SnapshotRestore {
+    snapshot: Snapshot,
+    app_hash: Vec<u8>,
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+fn chunk_hash(data: &[u8]) -> [u8; 32] {
+    let hash = Params::new()
+        .personal(b"ZCVote_Snapshot_")
+        .hash_length(32)
+        .hash(data);
+    hash.as_bytes().try_into().unwrap()
+}
+
+async fn dump_table(conn: &mut SqliteConnection, table: &str) -> ZCVResult<TableDump> {
+    let rows = query(&format!("SELECT * FROM {table}"))
+        .fetch_all(&mut *conn)
+        .await?;
+    let mut columns = vec![];
+    if let Some(row) = rows.first() {
+        columns = row.columns().iter().map(|c| c.name().to_string()).collect();
+    }
+    let mut values = vec![];
+    for row in rows.iter() {
+        let mut vs = vec![];
+        for i in 0..columns.len() {
+            let v = row.try_get_raw(i)?;
+            let v = if v.is_null() {
+                SqlValue::Null
+            } else {
+                match v.type_info().name() {
+                    "INTEGER" | "BOOLEAN" => SqlValue::Integer(row.try_get(i)?),
+                    "REAL" => SqlValue::Real(row.try_get(i)?),
+                    "TEXT" => SqlValue::Text(row.try_get(i)?),
+                    _ => SqlValue::Blob(row.try_get(i)?),
+                }
+            };
+            vs.push(v);
+        }
+        values.push(vs);
+    }
+    Ok(TableDump {
+        name: table.to_string(),
+        columns,
+        rows: values,
+    })
+}
+
+async fn restore_table(conn: &mut SqliteConnection, table: TableDump) -> ZCVResult<()> {
+    let TableDump {
+        name,
+        columns,
+        rows,
+    } = table;
+    if !SNAPSHOT_TABLES.contains(&name.as_str()) {
+        return Err(anyhow!("Unexpected table {name} in snapshot").into());
+    }
+    query(&format!("DELETE FROM {name}"))
+        .execute(&mut *conn)
+        .await?;
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let placeholders = vec!["?"; columns.len()].join(",");
+    let sql = format!(
+        "INSERT INTO {name}({}) VALUES ({placeholders})",
+        columns.join(",")
+    );
+    for row in rows {
+        let mut q = query(&sql);
+        for v in row {
+            q = match v {
+                SqlValue::Null => q.bind(None::<i64>),
+                SqlValue::Integer(v) => q.bind(v),
+                SqlValue::Real(v) => q.bind(v),
+                SqlValue::Text(v) => q.bind(v),
+                SqlValue::Blob(v) => q.bind(v),
+            };
+        }
+        q.execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+// Recompute the state derived from the ballots instead of trusting
+// the peer: the cmx tree of every election with its roots by vote height.
+// The nullifier tree is rebuilt by load()
+async fn rebuild_state(conn: &mut SqliteConnection) -> ZCVResult<()> {
+    let hasher = OrchardHasher::default();
+    for (election, ..) in list_vote_elections(&mut *conn).await? {
+        let domain = election.domain.as_slice();
+        let (_, nf_root, cmx_tree) = get_vote_election(&mut *conn, domain).await?;
+        let (_, mut cmx_tree) = read_roots(&nf_root, &cmx_tree)?;
+        clear_cmx_roots(&mut *conn, domain).await?;
+        store_cmx_root(&mut *conn, domain, &cmx_tree.root(&hasher), election.end).await?;
+        let cmxs = list_action_cmxs(&mut *conn, domain).await?;
+        for (i, (height, cmx)) in cmxs.iter().enumerate() {
+            let cmx: [u8; 32] = cmx
+                .as_slice()
+                .try_into()
+                .map_err(|_| anyhow!("Invalid cmx in snapshot"))?;
+            let cmx = MerkleHashOrchard::from_bytes(&cmx)
+                .into_option()
+                .ok_or(anyhow!("Invalid cmx in snapshot"))?;
+            cmx_tree.append(&hasher, cmx.to_bytes());
+            // Like finalize_block, the root is stored after the last ballot of a block
+            if cmxs.get(i + 1).is_none_or(|(h, _)| h != height) {
+                store_cmx_root(&mut *conn, domain, &cmx_tree.root(&hasher), *height).await?;
+            }
+        }
+        if let Some((height, _)) = cmxs.last() {
+            store_vote_height(&mut *conn, domain, *height).await?;
+        }
+        store_vote_frontier(&mut *conn, domain, &cmx_tree).await?;
+    }
+    Ok(())
+}
+
+// Dump the state tables, split them in chunks and store
+// them in the database. It runs in the background after the commit
+// of the block at height, on its own connection, so the tables are
+// read in a transaction to get a consistent state
+pub async fn take_snapshot(pool: SqlitePool, height: u32) -> ZCVResult<()> {
+    let mut conn = pool.acquire().await?;
+    let mut db_tx = conn.begin().await?;
+    let (block_height, _) = get_block_state(&mut db_tx).await?;
+    if block_height != height {
+        // The next block is already committed
+        tracing::info!("Skipped snapshot at height {height}");
+        return Ok(());
+    }
+    let mut tables = vec![];
+    for table in SNAPSHOT_TABLES {
+        tables.push(dump_table(&mut db_tx, table).await?);
+    }
+    db_tx.rollback().await?;
+    let data = bincode::encode_to_vec(&tables, bincode::config::standard()).anyhow()?;
+    let chunks: Vec<_> = data
+        .chunks(SNAPSHOT_CHUNK_SIZE)
+        .map(|c| c.to_vec())
+        .collect();
+    // metadata has the hashes of every chunk so that they can be
+    // verified as they arrive
+    let metadata: Vec<u8> = chunks.iter().flat_map(|c| chunk_hash(c)).collect();
+    let snapshot = Snapshot {
+        height: height as u64,
+        format: SNAPSHOT_FORMAT,
+        chunks: chunks.len() as u32,
+        hash: chunk_hash(&data).to_vec().into(),
+        metadata: metadata.into(),
+    };
+    store_snapshot(&mut conn, &snapshot, &chunks).await?;
+    prune_snapshots(&mut conn, SNAPSHOT_KEEP).await?;
+    tracing::info!("Snapshot at height {height}: {} chunks", chunks.len());
+    Ok(())
+}
+
+impl ServerState {
+    pub async fn list_snapshots(&self) -> ZCVResult<Vec<Snapshot>> {
+        let mut conn = self.pool.acquire().await?;
+        list_snapshots(&mut conn).await
+    }
+
+    pub async fn load_snapshot_chunk(
+        &self,
+        height: u64,
+        format: u32,
+        chunk: u32,
+    ) -> ZCVResult<Vec<u8>> {
+        let mut conn = self.pool.acquire().await?;
+        let chunk = get_snapshot_chunk(&mut conn, height, format, chunk).await?;
+        Ok(chunk.unwrap_or_default())
+    }
+
+    // app_hash is the trusted app hash at the snapshot height
+    pub fn offer_snapshot(
+        &mut self,
+        snapshot: Option<Snapshot>,
+        app_hash: &[u8],
+    ) -> ResponseOfferSnapshot {
+        use response_offer_snapshot::Result;
+        let result = match snapshot {
+            Some(snapshot) if snapshot.format != SNAPSHOT_FORMAT => Result::RejectFormat,
+            Some(snapshot)
+                if snapshot.chunks == 0
+                    || snapshot.metadata.len() != snapshot.chunks as usize * 32 =>
+            {
+                Result::Reject
+            }
+            Some(snapshot) => {
+                tracing::info!("Restoring snapshot at height {}", snapshot.height);
+                self.restore = Some(SnapshotRestore {
+                    chunks: vec![None; snapshot.chunks as usize],
+                    snapshot,
+                    app_hash: app_hash.to_vec(),
+                });
+                Result::Accept
+            }
+            None => Result::Reject,
+        };
+        ResponseOfferSnapshot {
+            result: result as i32,
+        }
+    }
+
+    pub async fn apply_snapshot_chunk(
+        &mut self,
+        index: u32,
+        chunk: Vec<u8>,
+        sender: String,
+    ) -> ZCVResult<ResponseApplySnapshotChunk> {
+        use response_apply_snapshot_chunk::Result;
+        let response = |result: Result| ResponseApplySnapshotChunk {
+            result: result as i32,
+            ..ResponseApplySnapshotChunk::default()
+        };
+        let Some(restore) = self.restore.as_mut() else {
+            return Ok(response(Result::Abort));
+        };
+        let i = index as usize;
+        if i >= restore.chunks.len() {
+            return Ok(response(Result::RejectSnapshot));
+        }
+        if chunk_hash(&chunk) != restore.snapshot.metadata[i * 32..(i + 1) * 32] {
+            tracing::info!("Invalid snapshot chunk {index} from {sender}");
+            return Ok(ResponseApplySnapshotChunk {
+                result: Result::Retry as i32,
+                refetch_chunks: vec![index],
+                reject_senders: vec![sender],
+            });
+        }
+        restore.chunks[i] = Some(chunk);
+        if restore.chunks.iter().any(|c| c.is_none()) {
+            return Ok(response(Result::Accept));
+        }
+
+        let restore = self.restore.take().unwrap();
+        let data: Vec<u8> = restore.chunks.into_iter().flatten().flatten().collect();
+        if chunk_hash(&data) != restore.snapshot.hash.as_ref() {
+            return Ok(response(Result::RejectSnapshot));
+        }
+        let (tables, _): (Vec<TableDump>, _) =
+            bincode::decode_from_slice(&data, bincode::config::standard()).anyhow()?;
+        // Authority from zcv.toml, before the restored state replaces it
+        let authority = self.authority;
+        let mut conn = self.pool.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+        for table in tables {
+            restore_table(&mut db_tx, table).await?;
+        }
+        let (height, _) = get_block_state(&mut db_tx).await?;
+        if height as u64 != restore.snapshot.height {
+            return Ok(response(Result::RejectSnapshot));
+        }
+        if let Err(e) = rebuild_state(&mut db_tx).await {
+            tracing::info!("Invalid snapshot: {e}");
+            return Ok(response(Result::RejectSnapshot));
+        }
+        db_tx.commit().await?;
+
+        // The restored state must match the app hash of the light client.
+        // It covers the rebuilt nullifiers and cmx trees, the elections,
+        // the validators and the admin state
+        let loaded = self.load().await;
+        if loaded.is_err()
+            || self.state_hash() != restore.app_hash.as_slice()
+            || self.apphash != restore.app_hash.as_slice()
+        {
+            tracing::info!("Snapshot does not match the app hash");
+            // Back to an empty state for the next snapshot
+            self.reset(&mut conn, authority).await?;
+            return Ok(response(Result::RejectSnapshot));
+        }
+        tracing::info!("Snapshot restored at height {height}");
+        Ok(response(Result::Accept))
+    }
+
+    // Clear the database and the state but keep what run_cometbft_app
+    // injected: the admission policy, the ballot tracker, the vote
+    // subscriptions and the configured authority
+    async fn reset(
+        &mut self,
+        conn: &mut SqliteConnection,
+        authority: Option<[u8; 32]>,
+    ) -> ZCVResult<()> {
+        drop_schema(&mut *conn).await?;
+        create_schema(&mut *conn).await?;
+        let state =
+            ServerState::new(self.pool.clone(), &self.lwd_url, self.skip_validation).await?;
+        *self = ServerState {
+            admission: self.admission.clone(),
+            check_witnesses_cache: self.check_witnesses_cache.clone(),
+            ballot_tracker: self.ballot_tracker.clone(),
+            block_notify: self.block_notify.clone(),
+            snapshot_interval: self.snapshot_interval,
+            ..state
+        };
+        if let Some(authority) = authority {
+            self.init_authority(authority).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use std::num::NonZeroUsize;
+
+use lru::LruCache;
+
+pub const BALLOT_TRACKER_SIZE: usize = 100_000;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TrackedBallot {
+    Pending,
+    // height is 0 when the ballot was dropped from the mempool
+    Rejected { height: u32, reason: String },
+}
+
+// Ballots seen by check_tx and finalize_block that are not in
+// the database. Included ballots are looked up in v_ballots
+pub struct BallotTracker {
+    ballots: LruCache<Vec<u8>, TrackedBallot>,
+}
+
+impl BallotTracker {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            ballots: LruCache::new(capacity),
+        }
+    }
+
+    pub fn pending(&mut self, sighash: &[u8]) {
+        self.ballots.put(sighash.to_vec(), TrackedBallot::Pending);
+    }
+
+    pub fn rejected(&mut self, sighash: &[u8], height: u32, reason: String) {
+        self.ballots
+            .put(sighash.to_vec(), TrackedBallot::Rejected { height, reason });
+    }
+
+    pub fn included(&mut self, sighash: &[u8]) {
+        self.ballots.pop(sighash);
+    }
+
+    pub fn get(&mut self, sighash: &[u8]) -> Option<TrackedBallot> {
+        self.ballots.get(sighash).cloned()
+    }
+}
+
+impl Default for BallotTracker {
+    fn default() -> Self {
+        Self::new(BALLOT_TRACKER_SIZE)
+    }
+}
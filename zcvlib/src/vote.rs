@@ -303,6 +303,14 @@ pub struct VoteResultItem {
     pub votes: u64,
 }
 
+// status is one of unknown, pending, included or rejected
+pub struct BallotStatusItem {
+    pub status: String,
+    pub height: u32,
+    pub itx: u32,
+    pub reason: String,
+}
+
 pub static PK: LazyLock<ProvingKey<Circuit>> = LazyLock::new(ProvingKey::build);
 pub static VK: LazyLock<VerifyingKey<Circuit>> = LazyLock::new(VerifyingKey::build);
 
@@ -27,7 +27,7 @@ impl Mutation {
     }
 
     async fn scan_ballots(id_account: i32, context: &GQLContext) -> FieldResult<bool> {
-        crate::api::simple::scan_ballots(id_account as u32, &context.0).await?;
+        crate::api::simple::scan_ballots(id_account as u32, false, &context.0).await?;
         Ok(true)
     }
 
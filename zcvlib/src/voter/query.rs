@@ -1,7 +1,7 @@
 #[cfg(feature = "graphql")]
 use bigdecimal::{BigDecimal, num_bigint::BigInt};
 #[cfg(feature = "graphql")]
-use juniper::{FieldError, FieldResult, Value, graphql_object};
+use juniper::{FieldError, FieldResult, GraphQLObject, Value, graphql_object};
 
 use crate::voter::GQLContext;
 
@@ -32,4 +32,25 @@ impl Query {
         let zec = BigDecimal::from_bigint(digits, 8);
         Ok(zec)
     }
+
+    // sighash of the ballot, hex encoded
+    async fn get_ballot_status(sighash: String, context: &GQLContext) -> FieldResult<BallotStatus> {
+        let sighash = hex::decode(&sighash)?;
+        let s = crate::api::simple::get_ballot_status(sighash, &context.0).await?;
+        Ok(BallotStatus {
+            status: s.status,
+            height: s.height as i32,
+            itx: s.itx as i32,
+            reason: s.reason,
+        })
+    }
+}
+
+#[cfg(feature = "graphql")]
+#[derive(GraphQLObject)]
+pub struct BallotStatus {
+    pub status: String,
+    pub height: i32,
+    pub itx: i32,
+    pub reason: String,
 }